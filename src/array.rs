@@ -0,0 +1,20 @@
+use ndarray::Array2;
+
+use crate::block::Block;
+use crate::scaling::{apply_scaling, scaling_factors};
+
+/// The scaled samples of a `STRM` block as a samples × components array.
+///
+/// Returns `None` if the block isn't a stream or has no numeric samples.
+pub fn stream_to_ndarray(block: &Block) -> Option<Array2<f64>> {
+    let children = match block {
+        Block::Stream(children) => children,
+        _ => return None,
+    };
+
+    let samples = children.iter().find_map(Block::samples)?;
+    let (components, mut values) = samples.to_f64();
+    apply_scaling(&mut values, components, &scaling_factors(children));
+
+    Array2::from_shape_vec((values.len() / components, components), values).ok()
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch};
+use arrow_schema::{DataType, Field, Schema};
+
+use crate::block::Block;
+use crate::fourcc::FourCC;
+use crate::scaling::{apply_scaling, scaling_factors};
+use crate::timing::{offset_sample_times, stream_payloads, time_offset};
+
+/// The scaled samples of every `fourcc` block as an Arrow record batch.
+///
+/// The batch has a `timestamp` column in seconds, from `sample_times` shifted by
+/// any `TIMO`, then one `Float64` column per component named
+/// `<fourcc>_<index>`, e.g. `GYRO_0`. Rows are grouped by device and stream,
+/// each timed from its own payloads.
+/// Returns `None` if no stream holds numeric `fourcc` samples.
+pub fn to_arrow(blocks: &[Block], fourcc: FourCC) -> Option<RecordBatch> {
+    let mut times = Vec::new();
+    let mut components = 0;
+    let mut values = Vec::new();
+    for payloads in stream_payloads(blocks, |block| block.fourcc() == fourcc) {
+        // Each device's stream is timed from its own payloads
+        let mut starts = Vec::new();
+        for (start, children) in payloads {
+            let samples = match children
+                .iter()
+                .filter(|child| child.fourcc() == fourcc)
+                .find_map(Block::samples)
+            {
+                Some(samples) => samples,
+                None => continue,
+            };
+
+            let (payload_components, mut payload) = samples.to_f64();
+            if components != 0 && payload_components != components {
+                continue;
+            }
+            components = payload_components;
+            apply_scaling(&mut payload, components, &scaling_factors(children));
+            starts.push((start, payload.len() / components, time_offset(children)));
+            values.extend(payload);
+        }
+        times.extend(offset_sample_times(&starts));
+    }
+
+    if components == 0 {
+        return None;
+    }
+
+    let mut fields = vec![Field::new("timestamp", DataType::Float64, false)];
+    let mut columns: Vec<ArrayRef> = vec![Arc::new(Float64Array::from(times))];
+    for component in 0..components {
+        fields.push(Field::new(
+            format!("{}_{}", fourcc, component),
+            DataType::Float64,
+            false,
+        ));
+        let column: Float64Array = values
+            .iter()
+            .skip(component)
+            .step_by(components)
+            .copied()
+            .collect();
+        columns.push(Arc::new(column));
+    }
+
+    RecordBatch::try_new(Arc::new(Schema::new(fields)), columns).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BlockBuilder;
+    use arrow_array::Array;
+
+    #[test]
+    fn times_interleaved_devices_separately() {
+        let mut builder = BlockBuilder::new();
+        for second in 0..2u64 {
+            builder.device(1).stream("Gyro").samples(
+                second * 1_000_000,
+                Block::Gyroscope(vec![[1, 1, 1], [1, 1, 1]]),
+            );
+            builder.device(2).stream("Gyro").samples(
+                second * 4_000_000,
+                Block::Gyroscope(vec![[2, 2, 2], [2, 2, 2]]),
+            );
+        }
+
+        let batch = to_arrow(&builder.build(), FourCC(*b"GYRO")).unwrap();
+        let times = batch
+            .column(0)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        assert_eq!(
+            times.values().to_vec(),
+            vec![0.0, 0.5, 1.0, 1.5, 0.0, 2.0, 4.0, 6.0]
+        );
+        assert_eq!(batch.column(1).len(), 8);
+    }
+}
@@ -0,0 +1,88 @@
+use crate::block::Block;
+use crate::timing::{offset_sample_times, stream_payloads, time_offset};
+
+/// An `AALP` sample, in dBFS.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AudioLevel {
+    pub rms: i8,
+    pub peak: i8,
+}
+
+/// Every AGC audio level sample paired with the index of the video frame it
+/// falls in, given the video's frame rate.
+///
+/// Each device's `AALP` stream is timed from its own payloads, and the samples
+/// are listed stream by stream.
+pub fn audio_levels_by_frame(blocks: &[Block], frame_rate: f64) -> Vec<(u32, AudioLevel)> {
+    let mut levels_by_frame = Vec::new();
+    for payloads in stream_payloads(blocks, |block| matches!(block, Block::AGCAudioLevel(_))) {
+        let mut counts = Vec::new();
+        let mut measurements = Vec::new();
+        for (start, children) in payloads {
+            let levels = children.iter().find_map(|child| match child {
+                Block::AGCAudioLevel(levels) => Some(levels),
+                _ => None,
+            });
+            if let Some(levels) = levels {
+                counts.push((start, levels.len(), time_offset(children)));
+                measurements.extend(levels);
+            }
+        }
+
+        levels_by_frame.extend(
+            offset_sample_times(&counts)
+                .into_iter()
+                .zip(measurements)
+                .map(|(time, &(rms, peak))| {
+                    let frame = (time * frame_rate).floor() as u32;
+                    (frame, AudioLevel { rms, peak })
+                }),
+        );
+    }
+
+    levels_by_frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::BlockBuilder;
+
+    #[test]
+    fn times_each_devices_levels_from_its_own_payloads() {
+        // Device 1 has a payload a second, device 2 one every two seconds
+        let mut builder = BlockBuilder::new();
+        for second in 0..3u64 {
+            builder.device(1).stream("Audio").samples(
+                second * 1_000_000,
+                Block::AGCAudioLevel(vec![(1, 1), (1, 1)]),
+            );
+            builder.device(2).stream("Audio").samples(
+                second * 2_000_000,
+                Block::AGCAudioLevel(vec![(2, 2), (2, 2)]),
+            );
+        }
+
+        let frames: Vec<(u32, i8)> = audio_levels_by_frame(&builder.build(), 2.0)
+            .into_iter()
+            .map(|(frame, level)| (frame, level.rms))
+            .collect();
+        assert_eq!(
+            frames,
+            vec![
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (3, 1),
+                (4, 1),
+                (5, 1),
+                (0, 2),
+                (2, 2),
+                (4, 2),
+                (6, 2),
+                (8, 2),
+                (10, 2),
+            ]
+        );
+    }
+}
@@ -0,0 +1,734 @@
+use std::convert::TryInto;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::face::Face;
+use crate::fourcc::FourCC;
+use crate::gps::AltitudeSystem;
+use crate::parser::CustomValue;
+use crate::type_descriptor::ComplexValue;
+
+pub type DeviceId = [u8; 4];
+
+/// A decoded block. Floats compare with IEEE semantics, so blocks holding NaN
+/// aren't equal to themselves.
+///
+/// New variants are added as more FourCCs get dedicated parsers, so matches
+/// outside this crate need a wildcard arm. Until a FourCC has a variant of its
+/// own it's still returned, as `Generic`, `Setting`, `Custom`, `Complex` or
+/// `Unknown`, for blocks that failed to parse leniently and complex blocks
+/// without a `TYPE`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[non_exhaustive]
+pub enum Block {
+    DeviceSource(Vec<Block>),
+    DeviceID(DeviceId),
+    DeviceName(String),
+    MediaUid(Vec<u32>),
+    Version(Vec<u8>), // VERS, e.g. [1, 1, 0], which may precede the first DEVC
+    Stream(Vec<Block>),
+    StartTimestamp(u64),
+    TotalSamples(u32),
+    StreamTimeSync(Vec<u32>),
+    TimeOffset(f32), // Seconds to shift the stream's sample times by
+    StreamName(String),
+    InputOrientation(String),
+    OrientationSetting(String),
+    CaptureMode(CaptureMode),
+    UnitsSI(String),
+    DisplayUnits(String), // UNIT, for units that aren't SI such as "deg"
+    ScalingFactorS(i16),
+    ScalingFactorL(Vec<i32>), // For GPS, or per-axis `s` factors. Should tidy this up.
+    Temperature(f32),
+    Acceleration(Vec<[i16; 3]>),
+    Gyroscope(Vec<[i16; 3]>),
+    ShutterSpeed(Vec<f32>),
+    WhiteBalance(Vec<u16>),
+    WhiteBalanceSigned(Vec<i16>),
+    WhiteBalanceFloat(Vec<f32>),
+    WhiteBalanceRGBGains(Vec<[f32; 3]>),
+    ISO(Vec<u16>),
+    ISOSigned(Vec<i16>),
+    ImageUniformity(Vec<f32>),
+    Type(String),
+    Custom(String, Vec<u8>),
+    Unknown(String, Vec<u8>), // Raw payload of a block that couldn't be decoded
+    Complex(String, Vec<Vec<ComplexValue>>), // Records decoded using the stream's TYPE
+    Generic(String, Values),
+    Setting(String, Values), // A known single-value block, decoded like `Generic`
+    Registered(
+        String,
+        #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_custom"))]
+        Arc<dyn CustomValue>,
+    ),
+    GPSF(u32),
+    GPSTimestamp(String),
+    GPSP(u16), // precision?
+    GPSA(AltitudeSystem),
+    GPS5(Vec<([i32; 5], Vec<u8>)>), // Trailing bytes are any extra per-record fields
+    CameraOrientation(Vec<[i16; 4]>),
+    ImageOrientation(Vec<[i16; 4]>),
+    GravityVector(Vec<[i16; 3]>),
+    Faces(Vec<Face>),
+    Matrix(Matrix),
+    WindProcessing(Vec<(u8, u8)>),
+    MicrophoneWet(Vec<(u8, u8, u8)>),
+    AGCAudioLevel(Vec<(i8, i8)>),
+    MRVFrameSkip(Vec<i16>), // Frames skipped, or repeated if negative; see `FrameSkip`
+    LRVO(i8),
+    LRVS(i8),
+    LRVFrameSkip(Vec<i16>), // As MSKP, for the low-resolution video
+}
+
+/// The camera mode from `CMOD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum CaptureMode {
+    Video,
+    TimeLapseVideo,
+    Looping,
+    SinglePhoto,
+    Photo,
+    NightPhoto,
+    BurstPhoto,
+    TimeLapsePhoto,
+    NightLapsePhoto,
+    TimeWarp,
+    Unknown(u32),
+}
+
+impl From<u32> for CaptureMode {
+    fn from(cmod: u32) -> Self {
+        match cmod {
+            12 => CaptureMode::Video,
+            13 => CaptureMode::TimeLapseVideo,
+            15 => CaptureMode::Looping,
+            16 => CaptureMode::SinglePhoto,
+            17 => CaptureMode::Photo,
+            18 => CaptureMode::NightPhoto,
+            19 => CaptureMode::BurstPhoto,
+            20 => CaptureMode::TimeLapsePhoto,
+            21 => CaptureMode::NightLapsePhoto,
+            24 => CaptureMode::TimeWarp,
+            other => CaptureMode::Unknown(other),
+        }
+    }
+}
+
+/// Row-major matrices, shaped according to the number of floats per record.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Matrix {
+    M2x2(Vec<[[f32; 2]; 2]>),
+    M3x3(Vec<[[f32; 3]; 3]>),
+    Other(Vec<f32>), // Unrecognised dimension, left flat
+}
+
+/// Values of a block without a dedicated parser, decoded from its data type.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Values {
+    U32(Vec<u32>),
+    I32(Vec<i32>),
+    F32(Vec<f32>),
+    I16(Vec<Vec<i16>>), // One inner vector per record, as wide as its size allows
+    U16(Vec<Vec<u16>>),
+    I8(Vec<i8>),
+    U8(Vec<u8>),
+    I64(Vec<i64>),
+    U64(Vec<u64>),
+    F64(Vec<f64>),
+    String(String),
+    Strings(Vec<String>),
+}
+
+impl Values {
+    pub fn len(&self) -> usize {
+        match self {
+            Values::U32(values) => values.len(),
+            Values::I32(values) => values.len(),
+            Values::F32(values) => values.len(),
+            Values::I16(records) => records.len(),
+            Values::U16(records) => records.len(),
+            Values::I8(values) => values.len(),
+            Values::U8(values) => values.len(),
+            Values::I64(values) => values.len(),
+            Values::U64(values) => values.len(),
+            Values::F64(values) => values.len(),
+            Values::String(_) => 1,
+            Values::Strings(values) => values.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Block {
+    /// Whether the block nests other blocks, i.e. is a `DEVC` or `STRM`.
+    pub fn is_container(&self) -> bool {
+        matches!(self, Block::DeviceSource(_) | Block::Stream(_))
+    }
+
+    /// Depth-first iterator over this block and all of its nested children.
+    pub fn walk(&self) -> impl Iterator<Item = &Block> {
+        descendants(std::slice::from_ref(self))
+    }
+
+    /// The `DVID` and `DVNM` of a `DEVC` block, or `None` for any other block
+    /// or a `DEVC` without a device ID.
+    pub fn device_info(&self) -> Option<(DeviceId, Option<String>)> {
+        let children = match self {
+            Block::DeviceSource(children) => children,
+            _ => return None,
+        };
+
+        let device_id = children.iter().find_map(|child| match child {
+            Block::DeviceID(device_id) => Some(*device_id),
+            _ => None,
+        })?;
+        let device_name = children.iter().find_map(|child| match child {
+            Block::DeviceName(device_name) => Some(device_name.clone()),
+            _ => None,
+        });
+
+        Some((device_id, device_name))
+    }
+
+    /// The `TICK` and `TOCK` of a `DEVC` block, the times in milliseconds at
+    /// which its payload starts and ends, or `None` for any other block or a
+    /// `DEVC` without both.
+    pub fn timestamp_range(&self) -> Option<(u32, u32)> {
+        let children = match self {
+            Block::DeviceSource(children) => children,
+            _ => return None,
+        };
+
+        let find = |name: &str| {
+            children.iter().find_map(|child| match child {
+                Block::Generic(key, Values::U32(values)) if key == name => values.first().copied(),
+                _ => None,
+            })
+        };
+
+        Some((find("TICK")?, find("TOCK")?))
+    }
+
+    /// The key the block was parsed from. Blocks with several possible keys
+    /// report the most common one, e.g. `MTRX` for `SMTR`.
+    pub fn fourcc(&self) -> FourCC {
+        let key = match self {
+            Block::DeviceSource(_) => b"DEVC",
+            Block::DeviceID(_) => b"DVID",
+            Block::DeviceName(_) => b"DVNM",
+            Block::MediaUid(_) => b"MUID",
+            Block::Version(_) => b"VERS",
+            Block::Stream(_) => b"STRM",
+            Block::StartTimestamp(_) => b"STMP",
+            Block::TotalSamples(_) => b"TSMP",
+            Block::StreamTimeSync(_) => b"STPS",
+            Block::StreamName(_) => b"STNM",
+            Block::InputOrientation(_) => b"ORIN",
+            Block::OrientationSetting(_) => b"OREN",
+            Block::CaptureMode(_) => b"CMOD",
+            Block::UnitsSI(_) => b"SIUN",
+            Block::DisplayUnits(_) => b"UNIT",
+            Block::ScalingFactorS(_) | Block::ScalingFactorL(_) => b"SCAL",
+            Block::Temperature(_) => b"TMPC",
+            Block::TimeOffset(_) => b"TIMO",
+            Block::Acceleration(_) => b"ACCL",
+            Block::Gyroscope(_) => b"GYRO",
+            Block::ShutterSpeed(_) => b"SHUT",
+            Block::WhiteBalance(_) | Block::WhiteBalanceSigned(_) | Block::WhiteBalanceFloat(_) => {
+                b"WBAL"
+            }
+            Block::WhiteBalanceRGBGains(_) => b"WRGB",
+            Block::ISO(_) | Block::ISOSigned(_) => b"ISOE",
+            Block::ImageUniformity(_) => b"UNIF",
+            Block::Type(_) => b"TYPE",
+            Block::Custom(name, _)
+            | Block::Unknown(name, _)
+            | Block::Complex(name, _)
+            | Block::Generic(name, _)
+            | Block::Setting(name, _)
+            | Block::Registered(name, _) => {
+                // Names are decoded from a four byte key, but keys that weren't
+                // valid UTF-8 may have been replaced lossily
+                return FourCC(name.as_bytes().try_into().unwrap_or(*b"????"));
+            }
+            Block::GPSF(_) => b"GPSF",
+            Block::GPSTimestamp(_) => b"GPSU",
+            Block::GPSP(_) => b"GPSP",
+            Block::GPSA(_) => b"GPSA",
+            Block::GPS5(_) => b"GPS5",
+            Block::CameraOrientation(_) => b"CORI",
+            Block::ImageOrientation(_) => b"IORI",
+            Block::GravityVector(_) => b"GRAV",
+            Block::Faces(_) => b"FACE",
+            Block::Matrix(_) => b"MTRX",
+            Block::WindProcessing(_) => b"WNDM",
+            Block::MicrophoneWet(_) => b"MWET",
+            Block::AGCAudioLevel(_) => b"AALP",
+            Block::MRVFrameSkip(_) => b"MSKP",
+            Block::LRVO(_) => b"LRVO",
+            Block::LRVS(_) => b"LRVS",
+            Block::LRVFrameSkip(_) => b"LSKP",
+        };
+
+        FourCC(*key)
+    }
+
+    /// Number of samples held by the block: the number of children for
+    /// containers, the number of measurements for arrays and 1 for scalars.
+    pub fn sample_count(&self) -> usize {
+        match self {
+            Block::DeviceSource(children) | Block::Stream(children) => children.len(),
+            Block::ScalingFactorL(values) => values.len(),
+            Block::StreamTimeSync(offsets) => offsets.len(),
+            Block::MediaUid(uid) => uid.len(),
+            Block::Acceleration(measurements)
+            | Block::Gyroscope(measurements)
+            | Block::GravityVector(measurements) => measurements.len(),
+            Block::CameraOrientation(measurements) | Block::ImageOrientation(measurements) => {
+                measurements.len()
+            }
+            Block::ShutterSpeed(measurements)
+            | Block::ImageUniformity(measurements)
+            | Block::WhiteBalanceFloat(measurements) => measurements.len(),
+            Block::WhiteBalance(measurements) | Block::ISO(measurements) => measurements.len(),
+            Block::WhiteBalanceSigned(measurements) | Block::ISOSigned(measurements) => {
+                measurements.len()
+            }
+            Block::WhiteBalanceRGBGains(measurements) => measurements.len(),
+            Block::Matrix(Matrix::M2x2(matrices)) => matrices.len(),
+            Block::Matrix(Matrix::M3x3(matrices)) => matrices.len(),
+            Block::Matrix(Matrix::Other(values)) => values.len(),
+            Block::GPS5(measurements) => measurements.len(),
+            Block::Faces(faces) => faces.len(),
+            Block::Generic(_, values) | Block::Setting(_, values) => values.len(),
+            Block::Complex(_, records) => records.len(),
+            Block::WindProcessing(measurements) => measurements.len(),
+            Block::MicrophoneWet(measurements) => measurements.len(),
+            Block::AGCAudioLevel(measurements) => measurements.len(),
+            Block::MRVFrameSkip(measurements) | Block::LRVFrameSkip(measurements) => {
+                measurements.len()
+            }
+            Block::DeviceID(_)
+            | Block::DeviceName(_)
+            | Block::Version(_)
+            | Block::StartTimestamp(_)
+            | Block::TotalSamples(_)
+            | Block::StreamName(_)
+            | Block::InputOrientation(_)
+            | Block::OrientationSetting(_)
+            | Block::CaptureMode(_)
+            | Block::UnitsSI(_)
+            | Block::DisplayUnits(_)
+            | Block::ScalingFactorS(_)
+            | Block::Temperature(_)
+            | Block::TimeOffset(_)
+            | Block::Type(_)
+            | Block::Custom(_, _)
+            | Block::Unknown(_, _)
+            | Block::Registered(_, _)
+            | Block::GPSF(_)
+            | Block::GPSTimestamp(_)
+            | Block::GPSP(_)
+            | Block::GPSA(_)
+            | Block::LRVO(_)
+            | Block::LRVS(_) => 1,
+        }
+    }
+
+    /// Approximate number of scalar values held by the block, for preallocating
+    /// output. Containers sum their children, each component of a vector counts
+    /// separately, strings count as one value and raw payloads count their bytes.
+    pub fn size_hint(&self) -> usize {
+        match self {
+            Block::DeviceSource(children) | Block::Stream(children) => {
+                children.iter().map(Block::size_hint).sum()
+            }
+            Block::ScalingFactorL(values) => values.len(),
+            Block::StreamTimeSync(offsets) => offsets.len(),
+            Block::MediaUid(uid) => uid.len(),
+            Block::Version(version) => version.len(),
+            Block::Acceleration(measurements)
+            | Block::Gyroscope(measurements)
+            | Block::GravityVector(measurements) => 3 * measurements.len(),
+            Block::CameraOrientation(measurements) | Block::ImageOrientation(measurements) => {
+                4 * measurements.len()
+            }
+            Block::ShutterSpeed(measurements)
+            | Block::ImageUniformity(measurements)
+            | Block::WhiteBalanceFloat(measurements) => measurements.len(),
+            Block::WhiteBalance(measurements) | Block::ISO(measurements) => measurements.len(),
+            Block::WhiteBalanceSigned(measurements)
+            | Block::ISOSigned(measurements)
+            | Block::MRVFrameSkip(measurements)
+            | Block::LRVFrameSkip(measurements) => measurements.len(),
+            Block::WhiteBalanceRGBGains(measurements) => 3 * measurements.len(),
+            Block::Custom(_, data) | Block::Unknown(_, data) => data.len(),
+            Block::Complex(_, records) => records.iter().map(Vec::len).sum(),
+            Block::Generic(_, values) | Block::Setting(_, values) => values.len(),
+            Block::GPS5(measurements) => 5 * measurements.len(),
+            Block::Faces(faces) => 6 * faces.len(),
+            Block::Matrix(Matrix::M2x2(matrices)) => 4 * matrices.len(),
+            Block::Matrix(Matrix::M3x3(matrices)) => 9 * matrices.len(),
+            Block::Matrix(Matrix::Other(values)) => values.len(),
+            Block::WindProcessing(measurements) => 2 * measurements.len(),
+            Block::MicrophoneWet(measurements) => 3 * measurements.len(),
+            Block::AGCAudioLevel(measurements) => 2 * measurements.len(),
+            Block::DeviceID(_)
+            | Block::DeviceName(_)
+            | Block::StartTimestamp(_)
+            | Block::TotalSamples(_)
+            | Block::StreamName(_)
+            | Block::InputOrientation(_)
+            | Block::OrientationSetting(_)
+            | Block::CaptureMode(_)
+            | Block::UnitsSI(_)
+            | Block::DisplayUnits(_)
+            | Block::ScalingFactorS(_)
+            | Block::Temperature(_)
+            | Block::TimeOffset(_)
+            | Block::Type(_)
+            | Block::Registered(_, _)
+            | Block::GPSF(_)
+            | Block::GPSTimestamp(_)
+            | Block::GPSP(_)
+            | Block::GPSA(_)
+            | Block::LRVO(_)
+            | Block::LRVS(_) => 1,
+        }
+    }
+
+    /// Size in bytes of the block when encoded as KLV: the 8 byte header plus
+    /// the payload padded to a 4 byte boundary.
+    ///
+    /// Strings are measured after decoding, so fixed-width string arrays may
+    /// come out slightly smaller than their original encoding, and faces are
+    /// measured in the oldest layout. Values from registered decoders are
+    /// opaque, so only their header is counted.
+    pub fn encoded_len(&self) -> usize {
+        let data_length = match self {
+            Block::DeviceSource(children) | Block::Stream(children) => {
+                return 8 + children.iter().map(Block::encoded_len).sum::<usize>();
+            }
+            Block::DeviceID(_) => 4,
+            Block::DeviceName(string)
+            | Block::StreamName(string)
+            | Block::InputOrientation(string)
+            | Block::OrientationSetting(string)
+            | Block::UnitsSI(string)
+            | Block::DisplayUnits(string)
+            | Block::Type(string)
+            | Block::GPSTimestamp(string) => string.len(),
+            Block::StartTimestamp(_) => 8,
+            Block::TotalSamples(_) | Block::GPSF(_) | Block::GPSA(_) => 4,
+            Block::CaptureMode(_) => 4, // Stored with varying widths
+            Block::ScalingFactorS(_) | Block::GPSP(_) => 2,
+            Block::ScalingFactorL(values) => 4 * values.len(),
+            Block::StreamTimeSync(offsets) => 4 * offsets.len(),
+            Block::MediaUid(uid) => 4 * uid.len(),
+            Block::Version(version) => version.len(),
+            Block::Temperature(_) | Block::TimeOffset(_) => 4,
+            Block::Acceleration(measurements)
+            | Block::Gyroscope(measurements)
+            | Block::GravityVector(measurements) => 6 * measurements.len(),
+            Block::CameraOrientation(measurements) | Block::ImageOrientation(measurements) => {
+                8 * measurements.len()
+            }
+            Block::ShutterSpeed(measurements)
+            | Block::ImageUniformity(measurements)
+            | Block::WhiteBalanceFloat(measurements) => 4 * measurements.len(),
+            Block::WhiteBalance(measurements) | Block::ISO(measurements) => 2 * measurements.len(),
+            Block::WhiteBalanceSigned(measurements)
+            | Block::ISOSigned(measurements)
+            | Block::MRVFrameSkip(measurements)
+            | Block::LRVFrameSkip(measurements) => 2 * measurements.len(),
+            Block::WhiteBalanceRGBGains(measurements) => 12 * measurements.len(),
+            Block::Custom(_, data) | Block::Unknown(_, data) => data.len(),
+            Block::Complex(_, records) => records
+                .iter()
+                .flatten()
+                .map(ComplexValue::encoded_size)
+                .sum(),
+            Block::Registered(_, _) => 0,
+            Block::Generic(_, Values::U32(values)) | Block::Setting(_, Values::U32(values)) => {
+                4 * values.len()
+            }
+            Block::Generic(_, Values::I32(values)) | Block::Setting(_, Values::I32(values)) => {
+                4 * values.len()
+            }
+            Block::Generic(_, Values::F32(values)) | Block::Setting(_, Values::F32(values)) => {
+                4 * values.len()
+            }
+            Block::Generic(_, Values::I16(records)) | Block::Setting(_, Values::I16(records)) => {
+                2 * records.iter().map(Vec::len).sum::<usize>()
+            }
+            Block::Generic(_, Values::U16(records)) | Block::Setting(_, Values::U16(records)) => {
+                2 * records.iter().map(Vec::len).sum::<usize>()
+            }
+            Block::Generic(_, Values::I8(values)) | Block::Setting(_, Values::I8(values)) => {
+                values.len()
+            }
+            Block::Generic(_, Values::U8(values)) | Block::Setting(_, Values::U8(values)) => {
+                values.len()
+            }
+            Block::Generic(_, Values::I64(values)) | Block::Setting(_, Values::I64(values)) => {
+                8 * values.len()
+            }
+            Block::Generic(_, Values::U64(values)) | Block::Setting(_, Values::U64(values)) => {
+                8 * values.len()
+            }
+            Block::Generic(_, Values::F64(values)) | Block::Setting(_, Values::F64(values)) => {
+                8 * values.len()
+            }
+            Block::Generic(_, Values::String(string))
+            | Block::Setting(_, Values::String(string)) => string.len(),
+            Block::Generic(_, Values::Strings(strings))
+            | Block::Setting(_, Values::Strings(strings)) => {
+                let width = strings.iter().map(String::len).max().unwrap_or(0);
+                width * strings.len()
+            }
+            Block::GPS5(measurements) => measurements
+                .iter()
+                .map(|(_, trailing)| 20 + trailing.len())
+                .sum(),
+            Block::Faces(faces) => 20 * faces.len(), // As id, x, y, w, h
+            Block::Matrix(Matrix::M2x2(matrices)) => 16 * matrices.len(),
+            Block::Matrix(Matrix::M3x3(matrices)) => 36 * matrices.len(),
+            Block::Matrix(Matrix::Other(values)) => 4 * values.len(),
+            Block::WindProcessing(measurements) => 2 * measurements.len(),
+            Block::MicrophoneWet(measurements) => 3 * measurements.len(),
+            Block::AGCAudioLevel(measurements) => 2 * measurements.len(),
+            Block::LRVO(_) | Block::LRVS(_) => 1,
+        };
+
+        8 + data_length.next_multiple_of(4)
+    }
+
+    /// Keeps only the samples in `range`, as counted by `sample_count`.
+    /// Containers and single-valued blocks are left unchanged.
+    pub(crate) fn retain_samples(&mut self, range: Range<usize>) {
+        fn retain<T: Clone>(values: &mut Vec<T>, range: Range<usize>) {
+            *values = values[range].to_vec();
+        }
+
+        match self {
+            Block::ScalingFactorL(values) => retain(values, range),
+            Block::StreamTimeSync(offsets) => retain(offsets, range),
+            Block::MediaUid(uid) => retain(uid, range),
+            Block::Acceleration(measurements)
+            | Block::Gyroscope(measurements)
+            | Block::GravityVector(measurements) => retain(measurements, range),
+            Block::CameraOrientation(measurements) | Block::ImageOrientation(measurements) => {
+                retain(measurements, range)
+            }
+            Block::ShutterSpeed(measurements)
+            | Block::ImageUniformity(measurements)
+            | Block::WhiteBalanceFloat(measurements) => retain(measurements, range),
+            Block::WhiteBalance(measurements) | Block::ISO(measurements) => {
+                retain(measurements, range)
+            }
+            Block::WhiteBalanceSigned(measurements)
+            | Block::ISOSigned(measurements)
+            | Block::MRVFrameSkip(measurements)
+            | Block::LRVFrameSkip(measurements) => retain(measurements, range),
+            Block::WhiteBalanceRGBGains(measurements) => retain(measurements, range),
+            Block::Complex(_, records) => retain(records, range),
+            Block::Generic(_, Values::U32(values)) | Block::Setting(_, Values::U32(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::I32(values)) | Block::Setting(_, Values::I32(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::F32(values)) | Block::Setting(_, Values::F32(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::I16(records)) | Block::Setting(_, Values::I16(records)) => {
+                retain(records, range)
+            }
+            Block::Generic(_, Values::U16(records)) | Block::Setting(_, Values::U16(records)) => {
+                retain(records, range)
+            }
+            Block::Generic(_, Values::I8(values)) | Block::Setting(_, Values::I8(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::U8(values)) | Block::Setting(_, Values::U8(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::I64(values)) | Block::Setting(_, Values::I64(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::U64(values)) | Block::Setting(_, Values::U64(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::F64(values)) | Block::Setting(_, Values::F64(values)) => {
+                retain(values, range)
+            }
+            Block::Generic(_, Values::Strings(values))
+            | Block::Setting(_, Values::Strings(values)) => retain(values, range),
+            Block::GPS5(measurements) => retain(measurements, range),
+            Block::Faces(faces) => retain(faces, range),
+            Block::Matrix(Matrix::M2x2(matrices)) => retain(matrices, range),
+            Block::Matrix(Matrix::M3x3(matrices)) => retain(matrices, range),
+            Block::Matrix(Matrix::Other(values)) => retain(values, range),
+            Block::WindProcessing(measurements) => retain(measurements, range),
+            Block::MicrophoneWet(measurements) => retain(measurements, range),
+            Block::AGCAudioLevel(measurements) => retain(measurements, range),
+            _ => {}
+        }
+    }
+}
+
+/// Registered values are opaque, so they're serialized as their `Debug` output.
+#[cfg(feature = "serde")]
+fn serialize_custom<S: serde::Serializer>(
+    value: &Arc<dyn CustomValue>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&format_args!("{:?}", value))
+}
+
+/// Depth-first iterator over `blocks` and all of their nested children.
+pub(crate) fn descendants<'a>(blocks: &'a [Block]) -> Box<dyn Iterator<Item = &'a Block> + 'a> {
+    Box::new(blocks.iter().flat_map(|block| {
+        let children: &[Block] = match block {
+            Block::DeviceSource(children) | Block::Stream(children) => children,
+            _ => &[],
+        };
+        std::iter::once(block).chain(descendants(children))
+    }))
+}
+
+/// Returns every `Stream` block whose `STNM` matches `name`, in file order.
+///
+/// Streams are repeated in each `DEVC` payload, so this typically yields one
+/// block per payload.
+pub fn streams_named<'a>(blocks: &'a [Block], name: &'a str) -> impl Iterator<Item = &'a Block> {
+    descendants(blocks).filter(move |block| match block {
+        Block::Stream(children) => children
+            .iter()
+            .any(|child| matches!(child, Block::StreamName(stream_name) if stream_name == name)),
+        _ => false,
+    })
+}
+
+/// The GPMF version from a `VERS` block, such as `[1, 1, 0]`, which some
+/// writers put before the first `DEVC`.
+pub fn gpmf_version(blocks: &[Block]) -> Option<&[u8]> {
+    descendants(blocks).find_map(|block| match block {
+        Block::Version(version) => Some(version.as_slice()),
+        _ => None,
+    })
+}
+
+/// The media unique ID from the first `MUID`, shared by every chapter of the
+/// same recording.
+pub fn media_uid(blocks: &[Block]) -> Option<Vec<u32>> {
+    descendants(blocks).find_map(|block| match block {
+        Block::MediaUid(uid) => Some(uid.clone()),
+        _ => None,
+    })
+}
+
+/// Asserts that two lists of blocks are equal, allowing floats to differ by up
+/// to `epsilon`, so tests aren't thrown by rounding.
+#[cfg(test)]
+pub(crate) fn assert_blocks_eq(left: &[Block], right: &[Block], epsilon: f64) {
+    assert_eq!(left.len(), right.len(), "{:?} != {:?}", left, right);
+    for (left, right) in left.iter().zip(right) {
+        match (left, right) {
+            (Block::DeviceSource(left), Block::DeviceSource(right))
+            | (Block::Stream(left), Block::Stream(right)) => assert_blocks_eq(left, right, epsilon),
+            _ => match (float_fields(left), float_fields(right)) {
+                (Some(left_floats), Some(right_floats))
+                    if std::mem::discriminant(left) == std::mem::discriminant(right)
+                        && left.fourcc() == right.fourcc()
+                        && left_floats.len() == right_floats.len() =>
+                {
+                    let close = left_floats
+                        .iter()
+                        .zip(&right_floats)
+                        .all(|(a, b)| a == b || (a - b).abs() <= epsilon);
+                    assert!(close, "{:?} != {:?} within {}", left, right, epsilon);
+                }
+                _ => assert_eq!(left, right),
+            },
+        }
+    }
+}
+
+/// Every number in a block holding floats, or `None` for blocks without
+/// floats, which `assert_blocks_eq` compares exactly.
+#[cfg(test)]
+fn float_fields(block: &Block) -> Option<Vec<f64>> {
+    fn widen<'a>(values: impl IntoIterator<Item = &'a f32>) -> Vec<f64> {
+        values.into_iter().map(|&value| f64::from(value)).collect()
+    }
+
+    let floats = match block {
+        Block::TimeOffset(value) | Block::Temperature(value) => vec![f64::from(*value)],
+        Block::ShutterSpeed(values)
+        | Block::ImageUniformity(values)
+        | Block::WhiteBalanceFloat(values)
+        | Block::Matrix(Matrix::Other(values))
+        | Block::Generic(_, Values::F32(values))
+        | Block::Setting(_, Values::F32(values)) => widen(values),
+        Block::Generic(_, Values::F64(values)) | Block::Setting(_, Values::F64(values)) => {
+            values.clone()
+        }
+        Block::WhiteBalanceRGBGains(gains) => widen(gains.iter().flatten()),
+        Block::Matrix(Matrix::M2x2(matrices)) => widen(matrices.iter().flatten().flatten()),
+        Block::Matrix(Matrix::M3x3(matrices)) => widen(matrices.iter().flatten().flatten()),
+        Block::Faces(faces) => faces
+            .iter()
+            .flat_map(|face| {
+                // A missing confidence only matches another missing one
+                let confidence = face.confidence.unwrap_or(f32::INFINITY);
+                let [x, y, w, h, confidence] =
+                    [face.x, face.y, face.w, face.h, confidence].map(f64::from);
+                [f64::from(face.id), x, y, w, h, confidence]
+            })
+            .collect(),
+        Block::Complex(_, records) => records
+            .iter()
+            .flatten()
+            .map(ComplexValue::to_f64)
+            .collect::<Option<_>>()?,
+        _ => return None,
+    };
+
+    Some(floats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compares_floats_within_epsilon() {
+        let left = [Block::Stream(vec![
+            Block::StreamName("Shutter".to_string()),
+            Block::ShutterSpeed(vec![0.1, 0.2]),
+        ])];
+        let right = [Block::Stream(vec![
+            Block::StreamName("Shutter".to_string()),
+            Block::ShutterSpeed(vec![0.1 + 1e-7, 0.2]),
+        ])];
+        assert_blocks_eq(&left, &right, 1e-6);
+    }
+
+    #[test]
+    #[should_panic]
+    fn floats_beyond_epsilon_differ() {
+        assert_blocks_eq(
+            &[Block::Temperature(40.0)],
+            &[Block::Temperature(40.5)],
+            1e-6,
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use crate::block::Block;
+
+/// Builds a tree of `DEVC` payloads, e.g. for synthetic test data, or to
+/// rebuild a parsed file with some blocks changed.
+///
+/// Each call adds to the most recent `DEVC` or `STRM`, so a tree is built in
+/// file order, e.g. `builder.device(1).stream("Accelerometer").block(accl)`.
+#[derive(Debug, Default, Clone)]
+pub struct BlockBuilder {
+    blocks: Vec<Block>,
+    in_stream: bool, // Whether blocks go in the last STRM rather than its DEVC
+}
+
+impl BlockBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a `DEVC` with a `DVID` of `id`.
+    pub fn device(&mut self, id: u32) -> &mut Self {
+        self.blocks
+            .push(Block::DeviceSource(vec![Block::DeviceID(id.to_be_bytes())]));
+        self.in_stream = false;
+        self
+    }
+
+    /// Adds a `DVNM` to the current `DEVC`, after which blocks go in the `DEVC`
+    /// until another stream is started.
+    pub fn device_name(&mut self, name: &str) -> &mut Self {
+        self.devc().push(Block::DeviceName(name.to_string()));
+        self
+    }
+
+    /// Starts a `STRM` named `name` in the current `DEVC`, starting one without
+    /// a `DVID` if there isn't one yet.
+    pub fn stream(&mut self, name: &str) -> &mut Self {
+        self.devc()
+            .push(Block::Stream(vec![Block::StreamName(name.to_string())]));
+        self.in_stream = true;
+        self
+    }
+
+    /// Adds `block` to the current `STRM`, or to the current `DEVC` before any
+    /// stream is started, or at the top level before any device is.
+    pub fn block(&mut self, block: Block) -> &mut Self {
+        let in_stream = self.in_stream;
+        let children = match self.blocks.last_mut() {
+            Some(Block::DeviceSource(children)) => children,
+            _ => {
+                self.blocks.push(block);
+                return self;
+            }
+        };
+
+        match children.last_mut() {
+            Some(Block::Stream(stream)) if in_stream => stream.push(block),
+            _ => children.push(block),
+        }
+        self
+    }
+
+    /// Adds a `STMP` of `start` microseconds and the `samples` starting then to
+    /// the current `STRM`, e.g. `.stream("Gyroscope").samples(0, gyro)`.
+    pub fn samples(&mut self, start: u64, samples: Block) -> &mut Self {
+        self.block(Block::StartTimestamp(start)).block(samples)
+    }
+
+    /// The blocks built so far.
+    pub fn build(&self) -> Vec<Block> {
+        self.blocks.clone()
+    }
+
+    /// The children of the current `DEVC`, ending any stream.
+    fn devc(&mut self) -> &mut Vec<Block> {
+        if !matches!(self.blocks.last(), Some(Block::DeviceSource(_))) {
+            self.blocks.push(Block::DeviceSource(Vec::new()));
+        }
+        self.in_stream = false;
+
+        match self.blocks.last_mut() {
+            Some(Block::DeviceSource(children)) => children,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Continues building after `blocks`, e.g. the result of parsing a file.
+impl From<Vec<Block>> for BlockBuilder {
+    fn from(blocks: Vec<Block>) -> Self {
+        BlockBuilder {
+            blocks,
+            in_stream: false,
+        }
+    }
+}
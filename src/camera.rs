@@ -0,0 +1,75 @@
+use crate::block::{descendants, Block, Values};
+
+/// The identification strings a camera writes alongside its telemetry.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CameraInfo {
+    pub model: Option<String>,     // MINF
+    pub firmware: Option<String>,  // FMWR
+    pub serial: Option<String>,    // CASN
+    pub lens: Option<String>,      // LINF
+    pub camera_id: Option<String>, // CINF, in hex
+}
+
+/// Collects the first `MINF`, `FMWR`, `CASN` and `LINF` strings found in
+/// `blocks`, along with the first `CINF`, a binary ID given in hex.
+pub fn camera_info(blocks: &[Block]) -> CameraInfo {
+    let mut info = CameraInfo::default();
+    for block in descendants(blocks) {
+        let (name, value) = match block {
+            Block::Generic(name, values) | Block::Setting(name, values) => {
+                (name.as_str(), text(values))
+            }
+            Block::Custom(name, bytes) | Block::Unknown(name, bytes) => {
+                (name.as_str(), Some(hex(bytes)))
+            }
+            _ => continue,
+        };
+
+        let field = match name {
+            "MINF" => &mut info.model,
+            "FMWR" => &mut info.firmware,
+            "CASN" => &mut info.serial,
+            "LINF" => &mut info.lens,
+            "CINF" => &mut info.camera_id,
+            _ => continue,
+        };
+        if field.is_none() {
+            *field = value;
+        }
+    }
+
+    info
+}
+
+fn text(values: &Values) -> Option<String> {
+    let text = match values {
+        Values::String(value) => value.clone(),
+        Values::Strings(values) => values.join(" "),
+        Values::U32(values) => values
+            .iter()
+            .map(|value| format!("{:08x}", value))
+            .collect(),
+        Values::U8(values) => values
+            .iter()
+            .map(|value| format!("{:02x}", value))
+            .collect(),
+        Values::I32(_)
+        | Values::F32(_)
+        | Values::I16(_)
+        | Values::U16(_)
+        | Values::I8(_)
+        | Values::I64(_)
+        | Values::U64(_)
+        | Values::F64(_) => return None,
+    };
+    let text = text.trim_end_matches('\0').trim();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text.to_string())
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
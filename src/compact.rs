@@ -0,0 +1,89 @@
+use crate::block::{Block, DeviceId};
+use crate::devc::Devc;
+
+/// Blocks that describe a stream rather than its samples, and so are usually
+/// repeated unchanged in every payload.
+const STATIC_FOURCCS: &[&[u8; 4]] = &[b"SIUN", b"UNIT", b"SCAL", b"TYPE", b"ORIN", b"MTRX"];
+
+/// A stream across every payload of the file, with the blocks it repeats in
+/// each payload kept only once.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CompactStream<'a> {
+    pub device: Option<DeviceId>,
+    pub device_name: Option<&'a str>,
+    pub name: Option<&'a str>,         // STNM
+    pub static_blocks: Vec<&'a Block>, // The same in every payload
+    pub payloads: Vec<Vec<&'a Block>>, // The rest of each payload's children
+}
+
+/// The streams of the top-level `DEVC` blocks, with `DVID`, `DVNM` and `STNM`
+/// hoisted out of every payload, along with units, scaling and other
+/// descriptive blocks when they're identical throughout.
+///
+/// A descriptive block that changes, or is missing from some payloads, is left
+/// in each payload as it was. Streams are matched across payloads by device
+/// and name, in the order each stream first appears.
+pub fn compact_streams(blocks: &[Block]) -> Vec<CompactStream<'_>> {
+    let mut streams: Vec<CompactStream> = Vec::new();
+    for devc in blocks.iter().filter_map(Devc::new) {
+        let (device, device_name) = (devc.device_id(), devc.device_name());
+        for stream in devc.streams() {
+            let payload: Vec<&Block> = stream
+                .children()
+                .iter()
+                .filter(|child| !matches!(child, Block::StreamName(_)))
+                .collect();
+
+            let name = stream.name();
+            match streams
+                .iter_mut()
+                .find(|existing| existing.device == device && existing.name == name)
+            {
+                Some(existing) => {
+                    existing.device_name = existing.device_name.or(device_name);
+                    existing.payloads.push(payload);
+                }
+                None => streams.push(CompactStream {
+                    device,
+                    device_name,
+                    name,
+                    static_blocks: Vec::new(),
+                    payloads: vec![payload],
+                }),
+            }
+        }
+    }
+
+    for stream in &mut streams {
+        hoist_static_blocks(stream);
+    }
+
+    streams
+}
+
+/// Moves each of `STATIC_FOURCCS` that's identical in every payload of
+/// `stream` into its `static_blocks`.
+fn hoist_static_blocks(stream: &mut CompactStream) {
+    for fourcc in STATIC_FOURCCS {
+        let first = match stream.payloads[0]
+            .iter()
+            .find(|child| child.fourcc().0 == **fourcc)
+        {
+            Some(&first) => first,
+            None => continue,
+        };
+        let unchanged = stream.payloads.iter().all(|payload| {
+            let mut matching = payload.iter().filter(|child| child.fourcc().0 == **fourcc);
+            matching.next() == Some(&first) && matching.next().is_none()
+        });
+        if !unchanged {
+            continue;
+        }
+
+        stream.static_blocks.push(first);
+        for payload in &mut stream.payloads {
+            payload.retain(|child| child.fourcc().0 != **fourcc);
+        }
+    }
+}
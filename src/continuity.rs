@@ -0,0 +1,68 @@
+use crate::block::{Block, DeviceId};
+use crate::devc::Devc;
+use crate::fourcc::FourCC;
+
+/// A payload holding a different number of samples than its stream's `TSMP`
+/// total grew by since the previous payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ContinuityGap {
+    pub devc: usize,   // Index of the top-level DEVC in `blocks`
+    pub expected: u32, // Growth in TSMP
+    pub actual: usize, // Samples decoded
+}
+
+impl ContinuityGap {
+    /// Samples recorded but not in the payload, or if negative, samples in
+    /// the payload more than once.
+    pub fn missing(&self) -> i64 {
+        i64::from(self.expected) - self.actual as i64
+    }
+}
+
+/// Checks every payload of the streams holding `fourcc` samples against the
+/// stream's running `TSMP` total, returning those that don't match in file
+/// order.
+///
+/// Streams are matched across payloads by device and name. The first payload
+/// of each stream has nothing to compare against, and payloads without a
+/// `TSMP` are skipped.
+pub fn sample_continuity(blocks: &[Block], fourcc: FourCC) -> Vec<ContinuityGap> {
+    // The last TSMP of each stream, keyed by device and name
+    type Key<'a> = (Option<DeviceId>, Option<&'a str>);
+    let mut totals: Vec<(Key, u32)> = Vec::new();
+    let mut gaps = Vec::new();
+    for (index, devc) in blocks.iter().enumerate() {
+        let devc = match Devc::new(devc) {
+            Some(devc) => devc,
+            None => continue,
+        };
+        for stream in devc.streams() {
+            let samples = stream
+                .children()
+                .iter()
+                .find(|child| child.fourcc() == fourcc);
+            let (actual, total) = match (samples, stream.total_samples()) {
+                (Some(samples), Some(total)) => (samples.sample_count(), total),
+                _ => continue,
+            };
+
+            let key = (devc.device_id(), stream.name());
+            match totals.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, previous)) => {
+                    let expected = total.wrapping_sub(*previous);
+                    if expected as usize != actual {
+                        gaps.push(ContinuityGap {
+                            devc: index,
+                            expected,
+                            actual,
+                        });
+                    }
+                    *previous = total;
+                }
+                None => totals.push((key, total)),
+            }
+        }
+    }
+
+    gaps
+}
@@ -0,0 +1,78 @@
+use crate::block::{Block, DeviceId};
+use crate::fourcc::FourCC;
+use crate::missing::MissingBlock;
+use crate::stream::Stream;
+
+/// A typed view over the children of a single `DEVC` payload.
+#[derive(Debug, Clone, Copy)]
+pub struct Devc<'a> {
+    children: &'a [Block],
+}
+
+impl<'a> Devc<'a> {
+    /// Views `block` as a device payload, or returns `None` if it isn't a `DEVC`.
+    pub fn new(block: &'a Block) -> Option<Self> {
+        match block {
+            Block::DeviceSource(children) => Some(Devc { children }),
+            _ => None,
+        }
+    }
+
+    pub fn children(&self) -> &'a [Block] {
+        self.children
+    }
+
+    /// The device ID from `DVID`.
+    pub fn device_id(&self) -> Option<DeviceId> {
+        self.children.iter().find_map(|child| match child {
+            Block::DeviceID(device_id) => Some(*device_id),
+            _ => None,
+        })
+    }
+
+    /// The device name from `DVNM`.
+    pub fn device_name(&self) -> Option<&'a str> {
+        self.children.iter().find_map(|child| match child {
+            Block::DeviceName(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The start of the payload in microseconds, from a `STMP` directly inside
+    /// the `DEVC`. Streams may carry their own, see `Stream::start_timestamp`.
+    pub fn start_timestamp(&self) -> Option<u64> {
+        self.children.iter().find_map(|child| match child {
+            Block::StartTimestamp(start) => Some(*start),
+            _ => None,
+        })
+    }
+
+    /// Like `device_id`, for payloads that must identify their device.
+    pub fn require_device_id(&self) -> Result<DeviceId, MissingBlock> {
+        self.device_id().ok_or_else(|| self.missing(b"DVID"))
+    }
+
+    /// Like `start_timestamp`, for payloads that must be timed.
+    pub fn require_start_timestamp(&self) -> Result<u64, MissingBlock> {
+        self.start_timestamp().ok_or_else(|| self.missing(b"STMP"))
+    }
+
+    /// Describes the payload by its device name or ID.
+    fn missing(&self, fourcc: &[u8; 4]) -> MissingBlock {
+        let container = match (self.device_name(), self.device_id()) {
+            (Some(name), _) => format!("device {}", name),
+            (None, Some(device_id)) => format!("device {:#x}", u32::from_be_bytes(device_id)),
+            (None, None) => "DEVC".to_string(),
+        };
+
+        MissingBlock {
+            container,
+            fourcc: FourCC(*fourcc),
+        }
+    }
+
+    /// The payload's streams, in file order.
+    pub fn streams(&self) -> impl Iterator<Item = Stream<'a>> {
+        self.children.iter().filter_map(Stream::new)
+    }
+}
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use crate::block::{Block, DeviceId};
+
+/// A device ID with a documented meaning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KnownDevice {
+    Camera, // DVID 1, the GoPro itself
+    Unknown(DeviceId),
+}
+
+impl From<DeviceId> for KnownDevice {
+    fn from(device_id: DeviceId) -> Self {
+        match u32::from_be_bytes(device_id) {
+            1 => KnownDevice::Camera,
+            _ => KnownDevice::Unknown(device_id),
+        }
+    }
+}
+
+impl fmt::Display for KnownDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KnownDevice::Camera => write!(f, "GoPro camera"),
+            KnownDevice::Unknown(device_id) => {
+                write!(f, "Device {:#x}", u32::from_be_bytes(*device_id))
+            }
+        }
+    }
+}
+
+/// A device and the streams it recorded across one or more `DEVC` payloads.
+#[derive(Debug)]
+pub struct Device<'a> {
+    pub id: Option<DeviceId>,
+    pub name: Option<&'a str>,
+    pub streams: Vec<&'a Block>,
+}
+
+impl Device<'_> {
+    /// The device ID as a `KnownDevice`, if there is one.
+    pub fn known_device(&self) -> Option<KnownDevice> {
+        self.id.map(KnownDevice::from)
+    }
+}
+
+/// Groups the streams of the top-level `DEVC` blocks by device.
+///
+/// Payloads are grouped by `DVID`, so a device's streams stay together even
+/// when its payloads are interleaved with another device's. Devices are listed
+/// in the order they first appear.
+pub fn devices(blocks: &[Block]) -> Vec<Device<'_>> {
+    let mut devices: Vec<Device> = Vec::new();
+    for block in blocks {
+        let children = match block {
+            Block::DeviceSource(children) => children,
+            _ => continue,
+        };
+
+        let mut id = None;
+        let mut name = None;
+        let mut streams = Vec::new();
+        for child in children {
+            match child {
+                Block::DeviceID(device_id) => id = Some(*device_id),
+                Block::DeviceName(device_name) => name = Some(device_name.as_str()),
+                Block::Stream(_) => streams.push(child),
+                _ => {}
+            }
+        }
+
+        match devices.iter_mut().find(|device| device.id == id) {
+            Some(device) => {
+                device.name = device.name.or(name);
+                device.streams.extend(streams);
+            }
+            _ => devices.push(Device { id, name, streams }),
+        }
+    }
+
+    devices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_slice;
+    use crate::parser::util::{container, klv};
+    use crate::stream::Stream;
+
+    #[test]
+    fn groups_interleaved_devices_by_id() {
+        let devc = |id: u8, name: &str| {
+            container(
+                b"DEVC",
+                &[
+                    klv(b"DVID", b'L', 4, 1, &[0, 0, 0, id]),
+                    container(
+                        b"STRM",
+                        &[klv(b"STNM", b'c', name.len() as u8, 1, name.as_bytes())],
+                    ),
+                ],
+            )
+        };
+        let input = [devc(1, "A1"), devc(2, "B1"), devc(1, "A2"), devc(2, "B2")].concat();
+        let blocks = parse_slice(&input).unwrap();
+
+        let devices = devices(&blocks);
+        let grouped: Vec<_> = devices
+            .iter()
+            .map(|device| {
+                let names: Vec<_> = device
+                    .streams
+                    .iter()
+                    .filter_map(|&stream| Stream::new(stream)?.name())
+                    .collect();
+                (device.id, names)
+            })
+            .collect();
+        assert_eq!(
+            grouped,
+            vec![
+                (Some([0, 0, 0, 1]), vec!["A1", "A2"]),
+                (Some([0, 0, 0, 2]), vec!["B1", "B2"]),
+            ]
+        );
+    }
+}
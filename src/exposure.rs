@@ -0,0 +1,154 @@
+use std::convert::TryFrom;
+
+use crate::block::Block;
+use crate::stream::Stream;
+use crate::timing::{sample_times, time_offset};
+
+/// The exposure of one sample of a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExposureSample {
+    pub time: Option<f64>,      // Seconds, from the stream's STMP and TIMO
+    pub shutter_s: Option<f32>, // SHUT
+    pub iso: Option<u16>,       // ISOE
+}
+
+/// Formats an exposure time in seconds as a shutter fraction such as `"1/240"`.
+/// Exposures of a second or more are given in whole seconds, e.g. `"2"`.
+pub fn shutter_fraction(seconds: f32) -> String {
+    if seconds <= 0.0 {
+        return String::from("0");
+    }
+
+    if seconds < 1.0 {
+        format!("1/{}", (1.0 / seconds).round())
+    } else {
+        format!("{}", seconds.round())
+    }
+}
+
+/// The stream's `SHUT` exposure times in seconds, each paired with its
+/// shutter fraction.
+pub fn shutter_speeds(stream: &Stream) -> Vec<(f32, String)> {
+    stream
+        .children()
+        .iter()
+        .filter_map(|child| match child {
+            Block::ShutterSpeed(measurements) => Some(measurements),
+            _ => None,
+        })
+        .flatten()
+        .map(|&seconds| (seconds, shutter_fraction(seconds)))
+        .collect()
+}
+
+/// The stream's `SHUT` and `ISOE` samples, paired by index. The series is as
+/// long as the longer of the two, with `None` where the other runs out.
+///
+/// Samples are spread evenly from the stream's start until the start of
+/// `next`, the same stream in the following payload, as in `sample_times`.
+/// Without `next` the sample period isn't known, so every sample shares the
+/// stream's start time. Negative ISOs are ignored.
+pub fn exposure_series(stream: &Stream, next: Option<&Stream>) -> Vec<ExposureSample> {
+    let children = stream.children();
+
+    let shutter: &[f32] = children
+        .iter()
+        .find_map(|child| match child {
+            Block::ShutterSpeed(measurements) => Some(measurements.as_slice()),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let iso: Vec<Option<u16>> = children
+        .iter()
+        .find_map(|child| match child {
+            Block::ISO(measurements) => Some(measurements.iter().map(|&iso| Some(iso)).collect()),
+            Block::ISOSigned(measurements) => Some(
+                measurements
+                    .iter()
+                    .map(|&iso| u16::try_from(iso).ok())
+                    .collect(),
+            ),
+            _ => None,
+        })
+        .unwrap_or_default();
+    let count = shutter.len().max(iso.len());
+    let times = stream.start_timestamp().map(|start| {
+        let mut payloads = vec![(start, count)];
+        payloads.extend(next.and_then(Stream::start_timestamp).map(|next| (next, 0)));
+        sample_times(&payloads)
+    });
+
+    (0..count)
+        .map(|index| ExposureSample {
+            time: times
+                .as_ref()
+                .map(|times| times[index] + time_offset(children)),
+            shutter_s: shutter.get(index).copied(),
+            iso: iso.get(index).copied().flatten(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_slice;
+    use crate::parser::util::{container, klv};
+
+    fn exposure_stream(start: u64) -> Block {
+        Block::Stream(vec![
+            Block::StartTimestamp(start),
+            Block::TimeOffset(0.5),
+            Block::ShutterSpeed(vec![0.01, 0.02, 0.04, 0.08]),
+            Block::ISO(vec![100, 200, 400]),
+        ])
+    }
+
+    #[test]
+    fn spreads_samples_until_the_next_payload() {
+        let (first, second) = (exposure_stream(1_000_000), exposure_stream(2_000_000));
+        let (first, second) = (Stream::new(&first).unwrap(), Stream::new(&second).unwrap());
+
+        let series = exposure_series(&first, Some(&second));
+        let times: Vec<_> = series.iter().map(|sample| sample.time).collect();
+        assert_eq!(times, vec![Some(1.5), Some(1.75), Some(2.0), Some(2.25)]);
+        assert_eq!(
+            series[3],
+            ExposureSample {
+                time: Some(2.25),
+                shutter_s: Some(0.08),
+                iso: None,
+            }
+        );
+
+        // Without the next payload every sample shares the start time
+        let series = exposure_series(&first, None);
+        assert!(series.iter().all(|sample| sample.time == Some(1.5)));
+    }
+
+    #[test]
+    fn parses_shutter_speeds_as_seconds_and_fractions() {
+        let seconds: Vec<u8> = [1.0f32 / 240.0, 1.0 / 60.0, 2.0]
+            .iter()
+            .flat_map(|value| value.to_be_bytes())
+            .collect();
+        let input = container(
+            b"STRM",
+            &[
+                klv(b"SHUT", b'f', 4, 3, &seconds),
+                klv(b"TSMP", b'L', 4, 1, &[0, 0, 0, 3]),
+            ],
+        );
+        let blocks = parse_slice(&input).unwrap();
+        let stream = Stream::new(&blocks[0]).unwrap();
+        assert_eq!(
+            shutter_speeds(&stream),
+            vec![
+                (1.0 / 240.0, "1/240".to_string()),
+                (1.0 / 60.0, "1/60".to_string()),
+                (2.0, "2".to_string()),
+            ]
+        );
+        assert_eq!(stream.children()[1], Block::TotalSamples(3));
+    }
+}
@@ -0,0 +1,108 @@
+use crate::type_descriptor::ComplexValue;
+
+/// A detected face from a `FACE` record. Coordinates are fractions of the
+/// frame, with `x` and `y` at the top left of the box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Face {
+    pub id: u32,
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+    pub confidence: Option<f32>, // Percent, when the firmware reports it
+}
+
+/// Builds faces from decoded `FACE` records, dividing each field by its `SCAL`
+/// factor.
+///
+/// Each firmware has its own layout, told apart by the number of fields:
+///
+/// - HERO6: `ID, x, y, w, h`
+/// - HERO7: `ID, x, y, w, h, unused[17], smile`
+/// - HERO8: `ID, x, y, w, h, confidence, smile`
+/// - HERO10: `version, confidence, ID, x, y, w, h, smile, blink`
+///
+/// Returns `None` for any other layout.
+pub(crate) fn faces_from_records(
+    records: &[Vec<ComplexValue>],
+    factors: &[f64],
+) -> Option<Vec<Face>> {
+    records
+        .iter()
+        .map(|record| {
+            let field = |index: usize| -> Option<f64> {
+                let factor = match factors {
+                    [factor] => *factor,
+                    _ => factors.get(index).copied().unwrap_or(1.0),
+                };
+                Some(record.get(index)?.to_f64()? / factor)
+            };
+
+            // Index of the ID, after which come x, y, w and h, and of the confidence
+            let (first, confidence) = match record.len() {
+                5 | 23 => (0, None),
+                7 => (0, Some(5)),
+                9 => (2, Some(1)),
+                _ => return None,
+            };
+            let confidence = match confidence {
+                Some(index) => Some(field(index)? as f32),
+                None => None,
+            };
+
+            Some(Face {
+                id: record.get(first)?.to_f64()? as u32,
+                x: field(first + 1)? as f32,
+                y: field(first + 2)? as f32,
+                w: field(first + 3)? as f32,
+                h: field(first + 4)? as f32,
+                confidence,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: u32, fields: &[f32]) -> Vec<ComplexValue> {
+        std::iter::once(ComplexValue::U32(id))
+            .chain(fields.iter().map(|&field| ComplexValue::F32(field)))
+            .collect()
+    }
+
+    #[test]
+    fn reads_hero8_confidence_after_the_box() {
+        let records = vec![record(3, &[0.1, 0.2, 0.3, 0.4, 90.0, 10.0])];
+        let faces = faces_from_records(&records, &[1.0]).unwrap();
+        assert_eq!(
+            faces,
+            vec![Face {
+                id: 3,
+                x: 0.1,
+                y: 0.2,
+                w: 0.3,
+                h: 0.4,
+                confidence: Some(90.0),
+            }]
+        );
+    }
+
+    #[test]
+    fn reads_hero10_version_first() {
+        let mut hero10 = vec![ComplexValue::U8(1), ComplexValue::F32(80.0)];
+        hero10.extend(record(3, &[0.1, 0.2, 0.3, 0.4, 0.0, 0.0]));
+        let faces = faces_from_records(&[hero10], &[1.0]).unwrap();
+        assert_eq!(faces[0].id, 3);
+        assert_eq!(faces[0].x, 0.1);
+        assert_eq!(faces[0].confidence, Some(80.0));
+    }
+
+    #[test]
+    fn rejects_unknown_layouts() {
+        let records = vec![record(3, &[0.1, 0.2, 0.3, 0.4, 0.5])];
+        assert_eq!(faces_from_records(&records, &[1.0]), None);
+    }
+}
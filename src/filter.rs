@@ -0,0 +1,226 @@
+use std::convert::TryFrom;
+use std::io::{self, prelude::*};
+
+use crate::block::Block;
+use crate::fourcc::FourCC;
+use crate::header::HeaderScanner;
+use crate::parser::util::{parse_klv_header, skip_padding, take_payload};
+use crate::parser::{parse_metadata, ParseError};
+
+/// A reader producing the GPMF of `source` with only the streams holding one
+/// of the given FourCCs, for writing smaller telemetry sidecar files.
+///
+/// Each top-level `DEVC` is read and filtered in turn, so the whole source is
+/// never held in memory. Device blocks such as `DVID` and `DVNM` are kept, but
+/// a `DEVC` left without any streams is dropped. Other top-level blocks are
+/// copied unchanged.
+#[derive(Debug)]
+pub struct FilteredGpmfReader<R> {
+    source: R,
+    fourccs: Vec<FourCC>,
+    pending: Vec<u8>, // Filtered bytes not yet read
+    position: usize,
+}
+
+impl<R: Read> FilteredGpmfReader<R> {
+    pub fn new(source: R, fourccs: &[FourCC]) -> Self {
+        FilteredGpmfReader {
+            source,
+            fourccs: fourccs.to_vec(),
+            pending: Vec::new(),
+            position: 0,
+        }
+    }
+
+    /// Reads and filters the next top-level block, returning `false` once the
+    /// source is exhausted.
+    fn fill(&mut self) -> io::Result<bool> {
+        let mut header_bytes = [0; 8];
+        let mut read = 0;
+        while read < header_bytes.len() {
+            match self.source.read(&mut header_bytes[read..])? {
+                0 => break,
+                n => read += n,
+            }
+        }
+        // Anything shorter than a header can only be padding
+        if read < header_bytes.len() {
+            return Ok(false);
+        }
+
+        let header = parse_klv_header(&header_bytes)
+            .map_err(|err| invalid_data(err.into()))?
+            .1;
+        let mut block = header_bytes.to_vec();
+        block.resize(8 + header.data_length().next_multiple_of(4), 0);
+        self.source.read_exact(&mut block[8..])?;
+
+        self.pending = if header.fourcc.0 == *b"DEVC" {
+            filter_devc(&block[8..], &self.fourccs).map_err(invalid_data)?
+        } else {
+            block
+        };
+        self.position = 0;
+
+        Ok(true)
+    }
+}
+
+impl<R: Read> Read for FilteredGpmfReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.pending.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+
+        let available = &self.pending[self.position..];
+        let length = available.len().min(buf.len());
+        buf[..length].copy_from_slice(&available[..length]);
+        self.position += length;
+
+        Ok(length)
+    }
+}
+
+/// Parses only the streams of `reader` holding one of the given FourCCs, along
+/// with their `STMP`, `SCAL` and other stream blocks. Other streams are
+/// skipped by their headers without being decoded.
+pub fn parse_only<R: Read>(reader: R, fourccs: &[FourCC]) -> Result<Vec<Block>, ParseError> {
+    parse_metadata(FilteredGpmfReader::new(reader, fourccs))
+}
+
+/// Rebuilds a `DEVC` from the children in `payload`, keeping only the streams
+/// holding one of `fourccs`. Returns nothing if no stream is kept.
+fn filter_devc(mut payload: &[u8], fourccs: &[FourCC]) -> Result<Vec<u8>, ParseError> {
+    let mut children = Vec::new();
+    let mut any_streams = false;
+    // Up to 3 bytes of padding may be left after the last child
+    while payload.len() >= 4 {
+        let (rest, header) = parse_klv_header(payload)?;
+        let (rest, stream) = take_payload(rest, &header)?;
+        let (rest, _padding) = skip_padding(rest, header.data_length())?;
+        let child = &payload[..payload.len() - rest.len()];
+
+        if header.fourcc.0 != *b"STRM" {
+            children.extend_from_slice(child);
+        } else if holds_any(stream, fourccs)? {
+            children.extend_from_slice(child);
+            any_streams = true;
+        }
+        payload = rest;
+    }
+
+    if !any_streams {
+        return Ok(Vec::new());
+    }
+
+    // Children are padded to 4 bytes, so longer payloads can be counted in words
+    let (size, count) = match u16::try_from(children.len()) {
+        Ok(count) => (1, count),
+        Err(_) => (
+            4,
+            u16::try_from(children.len() / 4).map_err(|_| ParseError::BlockTooLarge)?,
+        ),
+    };
+    let mut devc = b"DEVC".to_vec();
+    devc.extend_from_slice(&[0, size]);
+    devc.extend_from_slice(&count.to_be_bytes());
+    devc.extend(children);
+
+    Ok(devc)
+}
+
+fn holds_any(stream: &[u8], fourccs: &[FourCC]) -> Result<bool, ParseError> {
+    for header in HeaderScanner::new(stream) {
+        if fourccs.contains(&header?.1.fourcc) {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn invalid_data(err: ParseError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::util::{container, klv};
+
+    fn stream(fourcc: &[u8; 4], name: &[u8]) -> Vec<u8> {
+        container(
+            b"STRM",
+            &[
+                klv(b"STNM", b'c', 1, name.len() as u16, name),
+                klv(fourcc, b's', 6, 1, &[0, 1, 0, 2, 0, 3]),
+            ],
+        )
+    }
+
+    fn device_id(id: u8) -> Vec<u8> {
+        klv(b"DVID", b'L', 4, 1, &[0, 0, 0, id])
+    }
+
+    /// A camera with accelerometer and gyroscope streams, then a device with
+    /// only a magnetometer stream.
+    fn input() -> Vec<u8> {
+        [
+            container(
+                b"DEVC",
+                &[
+                    device_id(1),
+                    stream(b"ACCL", b"Accelerometer"),
+                    stream(b"GYRO", b"Gyroscope"),
+                ],
+            ),
+            container(b"DEVC", &[device_id(2), stream(b"MAGN", b"Magnetometer")]),
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn keeps_only_the_selected_streams() {
+        let mut filtered = Vec::new();
+        FilteredGpmfReader::new(&input()[..], &[FourCC(*b"ACCL")])
+            .read_to_end(&mut filtered)
+            .unwrap();
+
+        let expected = container(b"DEVC", &[device_id(1), stream(b"ACCL", b"Accelerometer")]);
+        assert_eq!(filtered, expected);
+    }
+
+    #[test]
+    fn keeps_everything_when_every_stream_is_selected() {
+        let fourccs = [b"ACCL", b"GYRO", b"MAGN"].map(|fourcc| FourCC(*fourcc));
+        let mut filtered = Vec::new();
+        FilteredGpmfReader::new(&input()[..], &fourccs)
+            .read_to_end(&mut filtered)
+            .unwrap();
+        assert_eq!(filtered, input());
+    }
+
+    #[test]
+    fn parses_only_the_selected_streams() {
+        let all = crate::parser::parse_slice(&input()).unwrap();
+        let accelerometer = match &all[0] {
+            Block::DeviceSource(children) => children[1].clone(),
+            block => panic!("expected a DEVC, got {:?}", block),
+        };
+
+        let blocks = parse_only(&input()[..], &[FourCC(*b"ACCL")]).unwrap();
+        assert_eq!(
+            blocks,
+            vec![Block::DeviceSource(vec![
+                Block::DeviceID([0, 0, 0, 1]),
+                accelerometer,
+            ])]
+        );
+        assert_eq!(
+            parse_only(&input()[..], &[FourCC(*b"XXXX")]).unwrap(),
+            vec![]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::ParseError;
+
+/// The four character key identifying a block, e.g. `ACCL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FourCC(pub [u8; 4]);
+
+impl From<[u8; 4]> for FourCC {
+    fn from(bytes: [u8; 4]) -> Self {
+        FourCC(bytes)
+    }
+}
+
+impl fmt::Display for FourCC {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(&self.0))
+    }
+}
+
+/// Serialized as its four characters, e.g. `"ACCL"`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FourCC {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl FromStr for FourCC {
+    type Err = ParseError;
+
+    /// Parses a key such as `"ACCL"`, which must be exactly four ASCII characters.
+    fn from_str(fourcc: &str) -> Result<Self, Self::Err> {
+        if !fourcc.is_ascii() {
+            return Err(ParseError::InvalidFourCC);
+        }
+
+        let bytes = fourcc
+            .as_bytes()
+            .try_into()
+            .map_err(|_| ParseError::InvalidFourCC)?;
+
+        Ok(FourCC(bytes))
+    }
+}
@@ -0,0 +1,469 @@
+use std::time::Duration;
+
+use crate::block::Block;
+use crate::fourcc::FourCC;
+#[cfg(feature = "chrono")]
+use crate::stream::Stream;
+use crate::timing::stream_sample_times;
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// Mean radius of the earth in metres, as used by the haversine formula.
+const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// A GPS5 record with its scaling factors applied.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsPoint {
+    pub latitude: f64,  // Degrees
+    pub longitude: f64, // Degrees
+    pub altitude: f64,  // Metres
+    pub speed_2d: f64,  // Metres per second
+    pub speed_3d: f64,  // Metres per second
+}
+
+/// Quality of the GPS lock, from `GPSF`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpsFix {
+    NoLock,
+    Fix2D,
+    Fix3D,
+    Unknown(u32),
+}
+
+impl From<u32> for GpsFix {
+    fn from(gpsf: u32) -> Self {
+        match gpsf {
+            0 => GpsFix::NoLock,
+            2 => GpsFix::Fix2D,
+            3 => GpsFix::Fix3D,
+            other => GpsFix::Unknown(other),
+        }
+    }
+}
+
+/// Reference for GPS altitudes, from `GPSA`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AltitudeSystem {
+    MeanSeaLevel, // MSLV
+    Ellipsoid,    // ELLI, the WGS84 ellipsoid
+    Other(FourCC),
+}
+
+impl From<FourCC> for AltitudeSystem {
+    fn from(gpsa: FourCC) -> Self {
+        match &gpsa.0 {
+            b"MSLV" => AltitudeSystem::MeanSeaLevel,
+            b"ELLI" => AltitudeSystem::Ellipsoid,
+            _ => AltitudeSystem::Other(gpsa),
+        }
+    }
+}
+
+/// A GPS point together with the fix, precision and time recorded alongside it
+/// in the same stream.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsRecord {
+    pub point: GpsPoint,
+    pub fix: Option<GpsFix>,
+    pub dop: Option<f32>, // Dilution of precision, from GPSP / 100
+    pub time: Option<DateTime<Utc>>,
+    pub altitude_system: Option<AltitudeSystem>,
+}
+
+impl GpsPoint {
+    /// Builds a point from a raw GPS5 record and the stream's `SCAL` factors,
+    /// which give one divisor per field.
+    pub fn from_gps5(values: [i32; 5], scaling_factors: &[i32]) -> Self {
+        let scaled = |index: usize| {
+            let divisor = scaling_factors.get(index).copied().unwrap_or(1);
+            values[index] as f64 / divisor as f64
+        };
+
+        GpsPoint {
+            latitude: scaled(0),
+            longitude: scaled(1),
+            altitude: scaled(2),
+            speed_2d: scaled(3),
+            speed_3d: scaled(4),
+        }
+    }
+
+    /// Ground speed (`speed_2d`) in kilometres per hour.
+    pub fn speed_kmh(&self) -> f64 {
+        self.speed_2d * 3.6
+    }
+
+    /// Ground speed (`speed_2d`) in miles per hour.
+    pub fn speed_mph(&self) -> f64 {
+        self.speed_2d * 3600.0 / 1609.344
+    }
+
+    /// Altitude in feet.
+    pub fn altitude_feet(&self) -> f64 {
+        self.altitude / 0.3048
+    }
+
+    /// Vertical speed in metres per second, unsigned, from the difference
+    /// between `speed_3d` and `speed_2d`. Zero when noise leaves the 3D speed
+    /// below the 2D speed.
+    pub fn vertical_speed(&self) -> f64 {
+        (self.speed_3d.powi(2) - self.speed_2d.powi(2))
+            .max(0.0)
+            .sqrt()
+    }
+
+    /// The point a fraction `t` of the way from `self` to `other`, blending
+    /// every field linearly.
+    fn lerp(&self, other: &GpsPoint, t: f64) -> GpsPoint {
+        let blend = |from: f64, to: f64| from + (to - from) * t;
+        GpsPoint {
+            latitude: blend(self.latitude, other.latitude),
+            longitude: blend(self.longitude, other.longitude),
+            altitude: blend(self.altitude, other.altitude),
+            speed_2d: blend(self.speed_2d, other.speed_2d),
+            speed_3d: blend(self.speed_3d, other.speed_3d),
+        }
+    }
+
+    /// Great-circle distance in metres to `other`, ignoring altitude.
+    pub fn distance_to(&self, other: &GpsPoint) -> f64 {
+        let (latitude_1, latitude_2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let delta_latitude = latitude_2 - latitude_1;
+        let delta_longitude = (other.longitude - self.longitude).to_radians();
+
+        let a = (delta_latitude / 2.0).sin().powi(2)
+            + latitude_1.cos() * latitude_2.cos() * (delta_longitude / 2.0).sin().powi(2);
+
+        2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+    }
+}
+
+/// Total distance in metres along a track, summing the haversine distance
+/// between consecutive points.
+pub fn track_distance(points: &[GpsPoint]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| pair[0].distance_to(&pair[1]))
+        .sum()
+}
+
+/// Ground speed in metres per second at each point, derived from the change in
+/// position rather than the reported speed.
+///
+/// `timestamps` are in seconds, one per point. If the lengths differ, only as
+/// many points as have timestamps are used. Each speed covers the interval
+/// ending at that point; the first point reuses the speed of the interval
+/// after it. Intervals with no elapsed time have a speed of zero.
+pub fn derived_speed(points: &[GpsPoint], timestamps: &[f64]) -> Vec<f64> {
+    let len = points.len().min(timestamps.len());
+    let (points, timestamps) = (&points[..len], &timestamps[..len]);
+
+    let mut speeds: Vec<f64> = points
+        .windows(2)
+        .zip(timestamps.windows(2))
+        .map(|(pair, times)| {
+            let elapsed = times[1] - times[0];
+            if elapsed > 0.0 {
+                pair[0].distance_to(&pair[1]) / elapsed
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    match speeds.first() {
+        Some(&first) => speeds.insert(0, first),
+        None => speeds.extend(points.iter().map(|_| 0.0)),
+    }
+
+    speeds
+}
+
+/// A GPS point that jumped implausibly far from the last good point before it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpsAnomaly {
+    pub index: usize,       // In the track as passed to `sanitize_track`
+    pub point: GpsPoint,    // As recorded
+    pub sign_flipped: bool, // Negating the longitude would have brought it in line
+}
+
+/// What `sanitize_track` does with anomalous points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpsCorrection {
+    Keep,        // Only report them
+    Drop,        // Remove them from the track
+    Interpolate, // Replace them with a linear blend of the good points either side
+}
+
+/// Finds points whose latitude or longitude moved more than `max_jump`
+/// degrees from the last good point, as happens when firmware records a
+/// longitude with the wrong sign, and corrects them as requested.
+///
+/// At GPS5's 18 Hz, a jump of more than a degree between samples can't be
+/// real. The first point is assumed to be good. Anomalies are returned in
+/// track order, with indices into the track as it was passed in.
+pub fn sanitize_track(
+    points: &mut Vec<GpsPoint>,
+    max_jump: f64,
+    correction: GpsCorrection,
+) -> Vec<GpsAnomaly> {
+    let within = |from: &GpsPoint, latitude: f64, longitude: f64| {
+        (latitude - from.latitude).abs() <= max_jump
+            && (longitude - from.longitude).abs() <= max_jump
+    };
+
+    let mut anomalies = Vec::new();
+    let mut last_good = match points.first() {
+        Some(first) => *first,
+        None => return anomalies,
+    };
+    for (index, point) in points.iter().enumerate().skip(1) {
+        if within(&last_good, point.latitude, point.longitude) {
+            last_good = *point;
+        } else {
+            anomalies.push(GpsAnomaly {
+                index,
+                point: *point,
+                sign_flipped: within(&last_good, point.latitude, -point.longitude),
+            });
+        }
+    }
+
+    let is_anomalous = |index: usize| {
+        anomalies
+            .binary_search_by_key(&index, |anomaly| anomaly.index)
+            .is_ok()
+    };
+    match correction {
+        GpsCorrection::Keep => {}
+        GpsCorrection::Drop => {
+            let mut index = 0;
+            points.retain(|_| {
+                index += 1;
+                !is_anomalous(index - 1)
+            });
+        }
+        GpsCorrection::Interpolate => {
+            for anomaly in &anomalies {
+                let before = (0..anomaly.index).rev().find(|&index| !is_anomalous(index));
+                let after = (anomaly.index + 1..points.len()).find(|&index| !is_anomalous(index));
+                points[anomaly.index] = match (before, after) {
+                    (Some(before), Some(after)) => {
+                        let t = (anomaly.index - before) as f64 / (after - before) as f64;
+                        points[before].lerp(&points[after], t)
+                    }
+                    (Some(before), None) => points[before],
+                    _ => continue,
+                };
+            }
+        }
+    }
+
+    anomalies
+}
+
+/// The GPS5 points of a stream, each paired with the stream's `GPSF`, `GPSP`,
+/// `GPSU` and `GPSA`.
+///
+/// `GPSU` is only recorded once per payload, so every point in the stream
+/// shares the same time. Returns an empty vector for streams without GPS5.
+#[cfg(feature = "chrono")]
+pub fn gps_points(stream: &Stream) -> Vec<GpsRecord> {
+    let children = stream.children();
+
+    let scaling_factors = gps_scaling_factors(children);
+    let fix = children.iter().find_map(|child| match child {
+        Block::GPSF(gpsf) => Some(GpsFix::from(*gpsf)),
+        _ => None,
+    });
+    let dop = children.iter().find_map(|child| match child {
+        Block::GPSP(gpsp) => Some(*gpsp as f32 / 100.0),
+        _ => None,
+    });
+    let time = children.iter().find_map(|child| match child {
+        Block::GPSTimestamp(gpsu) => parse_gpsu(gpsu),
+        _ => None,
+    });
+    let altitude_system = children.iter().find_map(|child| match child {
+        Block::GPSA(altitude_system) => Some(*altitude_system),
+        _ => None,
+    });
+
+    children
+        .iter()
+        .filter_map(|child| match child {
+            Block::GPS5(measurements) => Some(measurements),
+            _ => None,
+        })
+        .flatten()
+        .map(|(values, _)| GpsRecord {
+            point: GpsPoint::from_gps5(*values, &scaling_factors),
+            fix,
+            dop,
+            time,
+            altitude_system,
+        })
+        .collect()
+}
+
+/// What `gps_at` returns for times outside the recorded track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutOfRange {
+    Clamp,  // The first or last point
+    Reject, // `None`
+}
+
+/// The position at `time` since the start of the recording, interpolated
+/// between the GPS5 samples either side of it.
+///
+/// Sample times come from each stream's `STMP` and `TIMO`, so the samples
+/// bracketing `time` may be in different `DEVC` payloads. Returns `None` if
+/// there are no timed GPS5 samples.
+pub fn gps_at(blocks: &[Block], time: Duration, out_of_range: OutOfRange) -> Option<GpsPoint> {
+    let times = stream_sample_times(blocks);
+
+    let mut track = Vec::new();
+    for (block_index, devc) in blocks.iter().enumerate() {
+        let children = match devc {
+            Block::DeviceSource(children) => children,
+            _ => continue,
+        };
+        for (child_index, child) in children.iter().enumerate() {
+            let (children, stream_times) = match (child, times.get(&(block_index, child_index))) {
+                (Block::Stream(children), Some(stream_times)) => (children, stream_times),
+                _ => continue,
+            };
+            track.extend(
+                stream_times
+                    .iter()
+                    .copied()
+                    .zip(stream_gps_points(children)),
+            );
+        }
+    }
+    track.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let time = time.as_secs_f64();
+    let after = track.partition_point(|&(sample_time, _)| sample_time < time);
+    match (
+        after.checked_sub(1).map(|before| track[before]),
+        track.get(after),
+    ) {
+        (_, Some(&(after_time, after))) if after_time == time => Some(after),
+        (Some((before_time, before)), Some(&(after_time, after))) => {
+            Some(before.lerp(&after, (time - before_time) / (after_time - before_time)))
+        }
+        (Some((_, last)), None) if out_of_range == OutOfRange::Clamp => Some(last),
+        (None, Some(&(_, first))) if out_of_range == OutOfRange::Clamp => Some(first),
+        _ => None,
+    }
+}
+
+/// The scaled GPS5 points of a stream's children, or none if it has no GPS5.
+pub(crate) fn stream_gps_points(children: &[Block]) -> Vec<GpsPoint> {
+    let scaling_factors = gps_scaling_factors(children);
+    children
+        .iter()
+        .filter_map(|child| match child {
+            Block::GPS5(measurements) => Some(measurements),
+            _ => None,
+        })
+        .flatten()
+        .map(|(values, _)| GpsPoint::from_gps5(*values, &scaling_factors))
+        .collect()
+}
+
+/// A GPS stream's `SCAL` factors, as `GpsPoint::from_gps5` takes them.
+fn gps_scaling_factors(children: &[Block]) -> Vec<i32> {
+    children
+        .iter()
+        .find_map(|child| match child {
+            Block::ScalingFactorS(factor) => Some(vec![*factor as i32]),
+            Block::ScalingFactorL(factors) => Some(factors.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+/// Parses a `GPSU` timestamp of the form `yymmddhhmmss.sss`. Older firmware
+/// leaves off the fractional seconds, which `%.f` treats as optional.
+#[cfg(feature = "chrono")]
+pub(crate) fn parse_gpsu(gpsu: &str) -> Option<DateTime<Utc>> {
+    let gpsu = gpsu.trim_end_matches('\0');
+    NaiveDateTime::parse_from_str(gpsu, "%y%m%d%H%M%S%.f")
+        .ok()
+        .map(|time| DateTime::from_naive_utc_and_offset(time, Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64, longitude: f64) -> GpsPoint {
+        GpsPoint {
+            latitude,
+            longitude,
+            altitude: 0.0,
+            speed_2d: 0.0,
+            speed_3d: 0.0,
+        }
+    }
+
+    #[test]
+    fn a_single_point_has_no_distance() {
+        let points = [point(51.5, -0.1)];
+        assert_eq!(track_distance(&points), 0.0);
+        assert_eq!(derived_speed(&points, &[0.0]), vec![0.0]);
+        assert_eq!(track_distance(&[]), 0.0);
+        assert_eq!(derived_speed(&[], &[]), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn duplicate_coordinates_add_no_distance() {
+        let points = [point(51.5, -0.1), point(51.5, -0.1), point(51.501, -0.1)];
+        let step = points[1].distance_to(&points[2]);
+        assert!((step - 111.2).abs() < 0.1, "{}", step);
+
+        assert_eq!(track_distance(&points), step);
+        assert_eq!(
+            derived_speed(&points, &[0.0, 1.0, 2.0]),
+            vec![0.0, 0.0, step]
+        );
+        // A repeated fix with no elapsed time isn't an infinite speed
+        assert_eq!(derived_speed(&points[..2], &[1.0, 1.0]), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn derives_speed_only_for_timed_points() {
+        let points = [point(51.5, -0.1), point(51.501, -0.1), point(51.502, -0.1)];
+        assert_eq!(derived_speed(&points, &[0.0, 1.0]).len(), 2);
+        assert_eq!(derived_speed(&points[..1], &[0.0, 1.0]), vec![0.0]);
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn parses_gpsu_with_and_without_fractional_seconds() {
+        use chrono::{TimeZone, Timelike};
+
+        let whole = Utc.with_ymd_and_hms(2021, 3, 14, 15, 9, 26).unwrap();
+        assert_eq!(parse_gpsu("210314150926"), Some(whole));
+
+        let fractional = parse_gpsu("210314150926.535").unwrap();
+        assert_eq!(fractional.with_nanosecond(0), Some(whole));
+        assert_eq!(fractional.timestamp_subsec_millis(), 535);
+
+        // Firmware pads the string out to its declared size with NULs
+        assert_eq!(parse_gpsu("210314150926.535\0"), Some(fractional));
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    fn rejects_malformed_gpsu() {
+        assert_eq!(parse_gpsu(""), None);
+        assert_eq!(parse_gpsu("2103141509"), None);
+        assert_eq!(parse_gpsu("211314150926.535"), None);
+        assert_eq!(parse_gpsu("21031415O926"), None);
+    }
+}
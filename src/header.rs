@@ -0,0 +1,341 @@
+use std::convert::TryFrom;
+use std::io::{prelude::*, SeekFrom};
+
+use crate::fourcc::FourCC;
+use crate::parser::util::parse_klv_header;
+use crate::parser::ParseError;
+use crate::type_descriptor::type_size;
+
+/// The key, type and length fields at the start of every block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHeader {
+    pub fourcc: FourCC,
+    pub data_type: u8,
+    pub size: usize,
+    pub count: usize,
+}
+
+impl BlockHeader {
+    /// Length of the payload in bytes, excluding padding.
+    pub fn data_length(&self) -> usize {
+        self.size * self.count
+    }
+
+    /// Whether the payload is a list of nested blocks, as for `DEVC` and `STRM`.
+    pub fn is_nested(&self) -> bool {
+        self.data_type == 0
+    }
+
+    /// Checks that `size` holds a whole number of values of the data type, and
+    /// matches the record shape of FourCCs with fixed-size records such as
+    /// `ACCL`, failing with `ParseError::InvalidSize` if not.
+    ///
+    /// `GPS5` records only need to hold their five values, as some firmware
+    /// follows them with extra fields of other widths.
+    pub fn validate(&self) -> Result<(), ParseError> {
+        if &self.fourcc.0 == b"GPS5" {
+            return if self.size >= 20 {
+                Ok(())
+            } else {
+                Err(ParseError::InvalidSize)
+            };
+        }
+
+        let record_size = match &self.fourcc.0 {
+            b"ACCL" | b"GYRO" | b"GRAV" => Some(6),
+            b"CORI" | b"IORI" => Some(8),
+            _ => None,
+        };
+        let whole_values =
+            type_size(self.data_type).is_none_or(|width| self.size.is_multiple_of(width));
+
+        if whole_values && record_size.is_none_or(|record_size| self.size == record_size) {
+            Ok(())
+        } else {
+            Err(ParseError::InvalidSize)
+        }
+    }
+}
+
+/// Containers nested deeper than this are reported as an error by
+/// `HeaderScanner`. GPMF only nests `STRM` inside `DEVC`.
+const MAX_DEPTH: usize = 8;
+
+/// The position of a scan through nested blocks, shared by `HeaderScanner`
+/// and the reader-based scans.
+#[derive(Debug, Clone)]
+struct ScanState {
+    offset: usize,
+    len: usize,               // Of the whole input
+    ends: [usize; MAX_DEPTH], // End offsets of the containers being scanned
+    depth: usize,
+}
+
+impl ScanState {
+    fn new(len: usize) -> Self {
+        ScanState {
+            offset: 0,
+            len,
+            ends: [0; MAX_DEPTH],
+            depth: 0,
+        }
+    }
+
+    /// The offset of the next header and the end of the container holding
+    /// it, or `None` once the input is exhausted.
+    fn next_header(&mut self) -> Option<(usize, usize)> {
+        // Anything shorter than a FourCC can only be padding
+        while self.depth > 0 && self.offset + 4 > self.ends[self.depth - 1] {
+            self.offset = self.ends[self.depth - 1];
+            self.depth -= 1;
+        }
+        if self.offset + 4 > self.len {
+            return None;
+        }
+
+        let end = match self.depth {
+            0 => self.len,
+            depth => self.ends[depth - 1],
+        };
+        Some((self.offset, end))
+    }
+
+    /// Moves past the block with `header` at `offset`, or into it if it's a
+    /// container, failing if it runs past `end`.
+    fn advance(
+        &mut self,
+        offset: usize,
+        end: usize,
+        header: &BlockHeader,
+    ) -> Result<(), ParseError> {
+        let block_end = header
+            .data_length()
+            .checked_next_multiple_of(4)
+            .and_then(|length| (offset + 8).checked_add(length))
+            .filter(|&block_end| block_end <= end)
+            .ok_or(ParseError::Generic)?;
+
+        if header.is_nested() {
+            if self.depth == MAX_DEPTH {
+                return Err(ParseError::Generic);
+            }
+            // The container's padding may also pad its last child
+            self.ends[self.depth] = block_end;
+            self.depth += 1;
+            self.offset = offset + 8;
+        } else {
+            self.offset = block_end;
+        }
+
+        Ok(())
+    }
+
+    fn fail(&mut self, err: ParseError) -> Option<Result<(usize, BlockHeader), ParseError>> {
+        self.offset = self.len;
+        self.depth = 0;
+        Some(Err(err))
+    }
+}
+
+/// An iterator over every block's header and its offset in bytes from the
+/// start of the input, in order with containers before their children.
+///
+/// Payloads are skipped without being read, and nothing is allocated, so this
+/// is the cheapest way to walk the structure of a large file.
+#[derive(Debug, Clone)]
+pub struct HeaderScanner<'a> {
+    input: &'a [u8],
+    state: ScanState,
+}
+
+impl<'a> HeaderScanner<'a> {
+    pub fn new(input: &'a [u8]) -> Self {
+        HeaderScanner {
+            input,
+            state: ScanState::new(input.len()),
+        }
+    }
+}
+
+impl Iterator for HeaderScanner<'_> {
+    type Item = Result<(usize, BlockHeader), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (offset, end) = self.state.next_header()?;
+
+        let header = match parse_klv_header(&self.input[offset..end]) {
+            Ok((_, header)) => header,
+            Err(err) => return self.state.fail(err.into()),
+        };
+        if let Err(err) = self.state.advance(offset, end, &header) {
+            return self.state.fail(err);
+        }
+
+        Some(Ok((offset, header)))
+    }
+}
+
+/// Like `HeaderScanner`, but reading just the headers from a seekable reader
+/// and seeking past payloads. Offsets are from the reader's position when the
+/// scan starts.
+struct ReaderScanner<R> {
+    reader: R,
+    start: u64,    // Of the scan in the reader
+    position: u64, // Of the reader, relative to `start`
+    state: ScanState,
+}
+
+impl<R: Read + Seek> ReaderScanner<R> {
+    fn new(mut reader: R) -> Result<Self, ParseError> {
+        let start = reader.stream_position()?;
+        let len = reader.seek(SeekFrom::End(0))?.saturating_sub(start);
+        reader.seek(SeekFrom::Start(start))?;
+
+        Ok(ReaderScanner {
+            reader,
+            start,
+            position: 0,
+            state: ScanState::new(usize::try_from(len).unwrap_or(usize::MAX)),
+        })
+    }
+
+    fn read_header(&mut self, offset: usize, end: usize) -> Result<BlockHeader, ParseError> {
+        // The header must fit in its container
+        if end - offset < 8 {
+            return Err(ParseError::Generic);
+        }
+
+        if self.position != offset as u64 {
+            self.reader
+                .seek(SeekFrom::Start(self.start + offset as u64))?;
+        }
+        let mut header_bytes = [0; 8];
+        self.reader.read_exact(&mut header_bytes)?;
+        self.position = offset as u64 + 8;
+
+        Ok(parse_klv_header(&header_bytes)?.1)
+    }
+}
+
+impl<R: Read + Seek> Iterator for ReaderScanner<R> {
+    type Item = Result<(usize, BlockHeader), ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (offset, end) = self.state.next_header()?;
+
+        let header = match self.read_header(offset, end) {
+            Ok(header) => header,
+            Err(err) => return self.state.fail(err),
+        };
+        if let Err(err) = self.state.advance(offset, end, &header) {
+            return self.state.fail(err);
+        }
+
+        Some(Ok((offset, header)))
+    }
+}
+
+/// Checks whether any block, however deeply nested, has the given FourCC.
+///
+/// Only block headers are read, seeking past payloads, and the scan stops at
+/// the first match.
+pub fn contains_fourcc<T: Read + Seek>(f: T, fourcc: FourCC) -> Result<bool, ParseError> {
+    for header in ReaderScanner::new(f)? {
+        if header?.1.fourcc == fourcc {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Every block's header paired with its offset in bytes from the start of the
+/// file, in file order with containers before their children.
+///
+/// Only block headers are read, seeking past payloads without reading them.
+pub fn index<T: Read + Seek>(f: T) -> Result<Vec<(usize, BlockHeader)>, ParseError> {
+    ReaderScanner::new(f)?.collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::util::{container, klv};
+    use std::io::Cursor;
+
+    /// Counts the bytes read through it.
+    struct CountingReader<R> {
+        inner: R,
+        read: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.read += read;
+            Ok(read)
+        }
+    }
+
+    impl<R: Seek> Seek for CountingReader<R> {
+        fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+            self.inner.seek(pos)
+        }
+    }
+
+    fn input() -> Vec<u8> {
+        container(
+            b"DEVC",
+            &[
+                klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 1]),
+                container(
+                    b"STRM",
+                    &[
+                        klv(b"STNM", b'c', 1, 5, b"Gyros"),
+                        klv(b"GYRO", b's', 6, 100, &[0; 600]),
+                    ],
+                ),
+                klv(b"DVNM", b'c', 1, 6, b"Camera"),
+            ],
+        )
+    }
+
+    #[test]
+    fn indexes_a_reader_like_a_slice() {
+        let input = input();
+        let expected: Vec<_> = HeaderScanner::new(&input)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(expected.len(), 6);
+        assert_eq!(index(Cursor::new(&input)).unwrap(), expected);
+
+        // Offsets are from where the reader starts
+        let mut reader = Cursor::new([vec![0; 12], input.clone()].concat());
+        reader.set_position(12);
+        assert_eq!(index(reader).unwrap(), expected);
+    }
+
+    #[test]
+    fn reads_only_the_headers() {
+        let input = input();
+        let mut reader = CountingReader {
+            inner: Cursor::new(&input),
+            read: 0,
+        };
+        assert!(contains_fourcc(&mut reader, FourCC(*b"DVNM")).unwrap());
+        assert_eq!(reader.read, 6 * 8);
+
+        assert!(!contains_fourcc(Cursor::new(&input), FourCC(*b"ACCL")).unwrap());
+    }
+
+    #[test]
+    fn rejects_blocks_running_past_their_container() {
+        let mut input = input();
+        input.truncate(input.len() - 8);
+        assert!(matches!(
+            index(Cursor::new(&input)),
+            Err(ParseError::Generic)
+        ));
+        assert!(HeaderScanner::new(&input).any(|header| header.is_err()));
+    }
+}
@@ -0,0 +1,15 @@
+use std::io::{self, Write};
+
+use crate::block::Block;
+
+/// Writes each block as JSON on a line of its own, as newline-delimited JSON.
+///
+/// Containers are written whole, so each top-level `DEVC` becomes one line.
+pub fn to_ndjson<W: Write>(blocks: &[Block], mut writer: W) -> io::Result<()> {
+    for block in blocks {
+        serde_json::to_writer(&mut writer, block)?;
+        writer.write_all(b"\n")?;
+    }
+
+    Ok(())
+}
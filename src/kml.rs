@@ -0,0 +1,93 @@
+use std::io::{self, Write};
+
+use crate::block::{descendants, Block};
+use crate::gps::stream_gps_points;
+
+/// Writes the GPS5 points in `blocks` as a KML document for Google Earth.
+///
+/// When every point can be given a UTC time from `wall_clock_times`, the track
+/// is written as a `gx:Track` so it can be played back over time, and
+/// otherwise as a `LineString`. A file without GPS5 gives an empty document.
+pub fn to_kml<W: Write>(blocks: &[Block], mut writer: W) -> io::Result<()> {
+    let points: Vec<_> = descendants(blocks)
+        .filter_map(|block| match block {
+            Block::Stream(children) => Some(stream_gps_points(children)),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">"#
+    )?;
+    writeln!(writer, "<Document>")?;
+    if !points.is_empty() {
+        writeln!(writer, "<Placemark>")?;
+        match track_times(blocks, points.len()) {
+            Some(times) => {
+                writeln!(writer, "<gx:Track>")?;
+                writeln!(writer, "<altitudeMode>absolute</altitudeMode>")?;
+                for time in times {
+                    writeln!(writer, "<when>{}</when>", time)?;
+                }
+                for point in &points {
+                    writeln!(
+                        writer,
+                        "<gx:coord>{} {} {}</gx:coord>",
+                        point.longitude, point.latitude, point.altitude
+                    )?;
+                }
+                writeln!(writer, "</gx:Track>")?;
+            }
+            None => {
+                writeln!(writer, "<LineString>")?;
+                writeln!(writer, "<altitudeMode>absolute</altitudeMode>")?;
+                writeln!(writer, "<coordinates>")?;
+                for point in &points {
+                    writeln!(
+                        writer,
+                        "{},{},{}",
+                        point.longitude, point.latitude, point.altitude
+                    )?;
+                }
+                writeln!(writer, "</coordinates>")?;
+                writeln!(writer, "</LineString>")?;
+            }
+        }
+        writeln!(writer, "</Placemark>")?;
+    }
+    writeln!(writer, "</Document>")?;
+    writeln!(writer, "</kml>")?;
+
+    Ok(())
+}
+
+/// The UTC time of each of the `count` GPS5 points in `blocks` as an ISO 8601
+/// string, or `None` unless every one of them has one.
+#[cfg(feature = "chrono")]
+fn track_times(blocks: &[Block], count: usize) -> Option<Vec<String>> {
+    use crate::wall_clock::{wall_clock_times, SampleTime};
+    use chrono::SecondsFormat;
+
+    let times: Option<Vec<String>> = wall_clock_times(blocks)
+        .into_iter()
+        .filter(|stream_times| {
+            let children = stream_times.stream.children();
+            children.iter().any(|child| matches!(child, Block::GPS5(_)))
+        })
+        .flat_map(|stream_times| stream_times.times)
+        .map(|time| match time {
+            SampleTime::Utc(time) => Some(time.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            SampleTime::Relative(_) => None,
+        })
+        .collect();
+
+    times.filter(|times| times.len() == count)
+}
+
+#[cfg(not(feature = "chrono"))]
+fn track_times(_blocks: &[Block], _count: usize) -> Option<Vec<String>> {
+    None
+}
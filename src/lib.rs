@@ -0,0 +1,87 @@
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "arrow")]
+mod arrow;
+mod audio;
+mod block;
+mod builder;
+mod camera;
+mod compact;
+mod continuity;
+mod devc;
+mod device;
+mod exposure;
+mod face;
+mod filter;
+mod fourcc;
+mod gps;
+mod header;
+#[cfg(feature = "json")]
+mod json;
+mod kml;
+mod low_res_video;
+mod missing;
+mod parser;
+mod prune;
+mod records;
+mod samples;
+mod scaling;
+mod stream;
+mod timing;
+mod type_descriptor;
+#[cfg(feature = "chrono")]
+mod wall_clock;
+mod white_balance;
+
+#[cfg(feature = "ndarray")]
+pub use array::stream_to_ndarray;
+#[cfg(feature = "arrow")]
+pub use arrow::to_arrow;
+pub use audio::{audio_levels_by_frame, AudioLevel};
+pub use block::{
+    gpmf_version, media_uid, streams_named, Block, CaptureMode, DeviceId, Matrix, Values,
+};
+pub use builder::BlockBuilder;
+pub use camera::{camera_info, CameraInfo};
+pub use compact::{compact_streams, CompactStream};
+pub use continuity::{sample_continuity, ContinuityGap};
+pub use devc::Devc;
+pub use device::{devices, Device, KnownDevice};
+pub use exposure::{exposure_series, shutter_fraction, shutter_speeds, ExposureSample};
+pub use face::Face;
+pub use filter::{parse_only, FilteredGpmfReader};
+pub use fourcc::FourCC;
+pub use gps::{
+    derived_speed, gps_at, sanitize_track, track_distance, AltitudeSystem, GpsAnomaly,
+    GpsCorrection, GpsFix, GpsPoint, OutOfRange,
+};
+#[cfg(feature = "chrono")]
+pub use gps::{gps_points, GpsRecord};
+pub use header::{contains_fourcc, index, BlockHeader, HeaderScanner};
+#[cfg(feature = "json")]
+pub use json::to_ndjson;
+pub use kml::to_kml;
+pub use low_res_video::{low_res_video_sync, FrameSkip, LowResVideoSync};
+pub use missing::MissingBlock;
+#[cfg(feature = "memmap2")]
+pub use parser::parse_mmap;
+#[cfg(feature = "rayon")]
+pub use parser::parse_parallel;
+pub use parser::{
+    block_parser, parse_incremental, parse_metadata, parse_metadata_with, parse_one,
+    parse_one_with, parse_slice, parse_slice_with, BlockParser, BlockReader, CustomValue,
+    ParseError, ParseOptions, ParseState, Warning, WarningKind,
+};
+pub use prune::{prune_to_size, prune_to_size_by};
+pub use records::{to_long_records, Record};
+pub use samples::Samples;
+pub use scaling::{
+    apply_scaling, degrees_per_second, differentiate, integrate_orientation, scaling_factors,
+    GyroSample,
+};
+pub use stream::{Stream, StreamKind};
+pub use timing::{sample_rate_series, sample_times, slice_time_range, total_duration};
+pub use type_descriptor::{type_size, ComplexValue, TypeDescriptor};
+#[cfg(feature = "chrono")]
+pub use wall_clock::{wall_clock_times, SampleTime, StreamTimes};
+pub use white_balance::{white_balance, WhiteBalance};
@@ -0,0 +1,86 @@
+use crate::block::Block;
+use crate::stream::Stream;
+
+/// One `MSKP` or `LSKP` value: the number of frames the video skipped at that
+/// point, or if negative, the number of frames it repeated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameSkip {
+    pub count: i16,
+}
+
+impl FrameSkip {
+    /// Whether frames were dropped, so telemetry runs ahead of the video.
+    pub fn is_skip(&self) -> bool {
+        self.count > 0
+    }
+
+    /// Whether frames were repeated, so telemetry falls behind the video.
+    pub fn is_duplicate(&self) -> bool {
+        self.count < 0
+    }
+}
+
+/// The frame offset and skip blocks relating the low-resolution video (LRV) to
+/// the main video (MRV) within a stream.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LowResVideoSync<'a> {
+    pub offset: Option<i8>,                // LRVO
+    pub skip: Option<i8>,                  // LRVS
+    pub lrv_frame_skip: Option<&'a [i16]>, // LSKP
+    pub mrv_frame_skip: Option<&'a [i16]>, // MSKP
+}
+
+impl LowResVideoSync<'_> {
+    /// The `LSKP` values for the low-resolution video.
+    pub fn lrv_skips(&self) -> Vec<FrameSkip> {
+        frame_skips(self.lrv_frame_skip)
+    }
+
+    /// The `MSKP` values for the main video.
+    pub fn mrv_skips(&self) -> Vec<FrameSkip> {
+        frame_skips(self.mrv_frame_skip)
+    }
+}
+
+fn frame_skips(counts: Option<&[i16]>) -> Vec<FrameSkip> {
+    counts
+        .unwrap_or_default()
+        .iter()
+        .map(|&count| FrameSkip { count })
+        .collect()
+}
+
+/// Gathers a stream's `LRVO`, `LRVS`, `LSKP` and `MSKP` blocks, or returns
+/// `None` if it has none of them.
+pub fn low_res_video_sync<'a>(stream: &Stream<'a>) -> Option<LowResVideoSync<'a>> {
+    let children = stream.children();
+
+    let sync = LowResVideoSync {
+        offset: children.iter().find_map(|child| match child {
+            Block::LRVO(offset) => Some(*offset),
+            _ => None,
+        }),
+        skip: children.iter().find_map(|child| match child {
+            Block::LRVS(skip) => Some(*skip),
+            _ => None,
+        }),
+        lrv_frame_skip: children.iter().find_map(|child| match child {
+            Block::LRVFrameSkip(measurements) => Some(measurements.as_slice()),
+            _ => None,
+        }),
+        mrv_frame_skip: children.iter().find_map(|child| match child {
+            Block::MRVFrameSkip(measurements) => Some(measurements.as_slice()),
+            _ => None,
+        }),
+    };
+
+    if sync.offset.is_none()
+        && sync.skip.is_none()
+        && sync.lrv_frame_skip.is_none()
+        && sync.mrv_frame_skip.is_none()
+    {
+        return None;
+    }
+
+    Some(sync)
+}
@@ -0,0 +1,20 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::fourcc::FourCC;
+
+/// A block that a stream or device payload was expected to hold, returned by
+/// accessors such as `Stream::require_scaling_factors`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingBlock {
+    pub container: String, // Where the block is missing from, e.g. "stream ACCL"
+    pub fourcc: FourCC,
+}
+
+impl fmt::Display for MissingBlock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} missing required {}", self.container, self.fourcc)
+    }
+}
+
+impl Error for MissingBlock {}
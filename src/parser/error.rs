@@ -1,20 +1,136 @@
-use std::fmt::Debug;
+use std::error::Error as StdError;
+use std::fmt::{self, Debug};
+
+use nom::error::{Error, ErrorKind};
 
 #[derive(Debug)]
 pub enum ParseError {
     Generic,
-    ReadError,
+    ReadError(std::io::Error),
+    InvalidTypeDescriptor,
+    InvalidFourCC,
+    BlockTooLarge,
+    InvalidSize, // A size that doesn't fit the block's type, see `BlockHeader::validate`
+    TooManyBlocks, // More than `ParseOptions::max_blocks`
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let message = match self {
+            ParseError::Generic => "malformed GPMF data",
+            ParseError::ReadError(err) => return write!(f, "failed to read GPMF data: {}", err),
+            ParseError::InvalidTypeDescriptor => "invalid TYPE descriptor",
+            ParseError::InvalidFourCC => "invalid FourCC",
+            ParseError::BlockTooLarge => "block exceeds the maximum size",
+            ParseError::InvalidSize => "block size doesn't fit its data type",
+            ParseError::TooManyBlocks => "input has more blocks than allowed",
+        };
+        f.write_str(message)
+    }
+}
+
+impl StdError for ParseError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            ParseError::ReadError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// The error of the parsers that see `ParseOptions`, keeping the options'
+/// limits and the header size check apart from nom's own errors so that a
+/// parser can't trip them by failing with the same `ErrorKind`.
+#[derive(Debug, PartialEq)]
+pub(crate) enum BlockError<I> {
+    Nom(Error<I>),
+    TooLarge(I),      // Longer than `ParseOptions::max_block_bytes`
+    InvalidSize(I),   // A size that doesn't fit the block's type
+    TooManyBlocks(I), // More than `ParseOptions::max_blocks`
+}
+
+impl<I> nom::error::ParseError<I> for BlockError<I> {
+    fn from_error_kind(input: I, kind: ErrorKind) -> Self {
+        BlockError::Nom(Error::new(input, kind))
+    }
+
+    fn append(_input: I, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
+}
+
+impl<I> From<Error<I>> for BlockError<I> {
+    fn from(error: Error<I>) -> Self {
+        BlockError::Nom(error)
+    }
 }
 
-impl<T: Debug> From<nom::Err<T>> for ParseError {
-    fn from(err: nom::Err<T>) -> Self {
-        println!("{:?}", err);
+/// For `parse_one_with`, which returns nom's error type.
+impl<I> From<BlockError<I>> for Error<I> {
+    fn from(error: BlockError<I>) -> Self {
+        match error {
+            BlockError::Nom(error) => error,
+            BlockError::TooLarge(input) => Error::new(input, ErrorKind::TooLarge),
+            BlockError::InvalidSize(input) => Error::new(input, ErrorKind::LengthValue),
+            BlockError::TooManyBlocks(input) => Error::new(input, ErrorKind::Many0),
+        }
+    }
+}
+
+impl<I: Debug> From<nom::Err<BlockError<I>>> for ParseError {
+    fn from(err: nom::Err<BlockError<I>>) -> Self {
+        match err {
+            nom::Err::Error(error) | nom::Err::Failure(error) => match error {
+                BlockError::Nom(error) => ParseError::from(nom::Err::Error(error)),
+                BlockError::TooLarge(_) => ParseError::BlockTooLarge,
+                BlockError::InvalidSize(_) => ParseError::InvalidSize,
+                BlockError::TooManyBlocks(_) => ParseError::TooManyBlocks,
+            },
+            nom::Err::Incomplete(needed) => {
+                ParseError::from(nom::Err::<Error<I>>::Incomplete(needed))
+            }
+        }
+    }
+}
+
+impl<I: Debug> From<nom::Err<Error<I>>> for ParseError {
+    fn from(_err: nom::Err<Error<I>>) -> Self {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(error = ?_err, "parse failed");
         ParseError::Generic
     }
 }
 
 impl From<std::io::Error> for ParseError {
-    fn from(_err: std::io::Error) -> Self {
-        ParseError::ReadError
+    fn from(err: std::io::Error) -> Self {
+        ParseError::ReadError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{self, Read};
+
+    struct FailingReader;
+
+    impl Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk on fire"))
+        }
+    }
+
+    fn parse_boxed() -> Result<Vec<crate::block::Block>, Box<dyn StdError>> {
+        Ok(crate::parser::parse_metadata(FailingReader)?)
+    }
+
+    #[test]
+    fn read_errors_keep_their_cause() {
+        let err = parse_boxed().unwrap_err();
+        assert_eq!(err.to_string(), "failed to read GPMF data: disk on fire");
+
+        let source = err.source().unwrap();
+        let io_err = source.downcast_ref::<io::Error>().unwrap();
+        assert_eq!(io_err.to_string(), "disk on fire");
     }
 }
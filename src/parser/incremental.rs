@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::io::prelude::*;
+
+use crate::block::Block;
+
+use super::util::parse_klv_header;
+use super::{parse_one_limited, ParseError, ParseOptions};
+
+/// Bytes of a partially received block, kept between calls to
+/// `parse_incremental`.
+#[derive(Debug, Default)]
+pub struct ParseState {
+    buffer: Vec<u8>,
+    options: ParseOptions,
+    parsed: usize, // Bytes of complete blocks, for reporting progress
+}
+
+impl ParseState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_options(options: ParseOptions) -> Self {
+        ParseState {
+            buffer: Vec::new(),
+            options,
+            parsed: 0,
+        }
+    }
+
+    /// Number of bytes received but not yet parsed.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+}
+
+/// Parses the top-level blocks completed by `new_bytes`, buffering any partial
+/// block in `state` until the rest of it arrives.
+///
+/// Blocks completed before a bad block are returned first; the following call
+/// returns the error and discards the bad block.
+pub fn parse_incremental(
+    state: &mut ParseState,
+    new_bytes: &[u8],
+) -> Result<Vec<Block>, ParseError> {
+    state.buffer.extend_from_slice(new_bytes);
+
+    let mut blocks = Vec::new();
+    let mut consumed = 0;
+    while let Ok((_, header)) = parse_klv_header(&state.buffer[consumed..]) {
+        if state.options.block_too_large(header.data_length()) {
+            if !blocks.is_empty() {
+                break;
+            }
+            // The rest of the stream can't be aligned without the whole block
+            state.buffer.clear();
+            return Err(ParseError::BlockTooLarge);
+        }
+
+        // Wait for the whole block, so that only a truncated block is incomplete
+        let length = 8 + header.data_length().next_multiple_of(4);
+        let input = &state.buffer[consumed..];
+        if input.len() < length {
+            break;
+        }
+
+        match parse_one_limited(&input[..length], &state.options) {
+            Ok((_, block)) => {
+                if let Block::DeviceSource(_) = block {
+                    state
+                        .options
+                        .report_progress(state.parsed + consumed + length, None);
+                }
+                blocks.push(block);
+            }
+            Err(_) if !blocks.is_empty() => break,
+            Err(err) => {
+                let err = ParseError::from(err);
+                state.buffer.drain(..length);
+                state.parsed += length;
+                return Err(err);
+            }
+        }
+        consumed += length;
+    }
+
+    state.buffer.drain(..consumed);
+    state.parsed += consumed;
+    Ok(blocks)
+}
+
+/// An iterator over the top-level blocks of `reader`, reading
+/// [`ParseOptions::buffer_size`] bytes at a time and parsing each block as soon
+/// as it's complete.
+#[derive(Debug)]
+pub struct BlockReader<R> {
+    reader: R,
+    state: ParseState,
+    chunk: Vec<u8>,
+    blocks: VecDeque<Block>, // Parsed but not yet returned
+    finished: bool,
+}
+
+impl<R: Read> BlockReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        BlockReader {
+            reader,
+            chunk: vec![0; options.read_buffer_size()],
+            state: ParseState::with_options(options),
+            blocks: VecDeque::new(),
+            finished: false,
+        }
+    }
+}
+
+impl<R: Read> Iterator for BlockReader<R> {
+    type Item = Result<Block, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.blocks.is_empty() {
+            if self.finished {
+                return None;
+            }
+
+            let read = match self.reader.read(&mut self.chunk) {
+                Ok(read) => read,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err.into())),
+            };
+            match parse_incremental(&mut self.state, &self.chunk[..read]) {
+                Ok(blocks) => self.blocks.extend(blocks),
+                Err(err) => return Some(Err(err)),
+            }
+
+            if read == 0 && self.blocks.is_empty() {
+                self.finished = true;
+                // Anything shorter than a FourCC can only be padding
+                if self.state.buffered() >= 4 {
+                    return Some(Err(ParseError::Generic));
+                }
+            }
+        }
+
+        self.blocks.pop_front().map(Ok)
+    }
+}
@@ -1,4 +1,2055 @@
+use std::convert::TryInto;
+use std::io::prelude::*;
+
+use nom::branch::alt;
+use nom::bytes::streaming::{tag, take};
+use nom::character::streaming::one_of;
+use nom::error::ErrorKind;
+use nom::multi::count as many_count;
+use nom::number::streaming::{
+    be_f32, be_f64, be_i16, be_i32, be_i64, be_i8, be_u16, be_u32, be_u64, be_u8,
+};
+use nom::{IResult, Offset};
+
 mod error;
+mod incremental;
+mod options;
 pub mod util;
+mod warning;
 
+use crate::block::{Block, CaptureMode, Matrix, Values};
+use crate::face::faces_from_records;
+use crate::fourcc::FourCC;
+use crate::gps::AltitudeSystem;
+use crate::header::BlockHeader;
+use crate::scaling::scaling_factors;
+use crate::type_descriptor::TypeDescriptor;
+use error::BlockError;
 pub use error::ParseError;
+pub use incremental::{parse_incremental, BlockReader, ParseState};
+use options::Decoder;
+pub use options::{CustomValue, ParseOptions};
+use util::{decode_utf8, parse_klv_header, parse_size_count, skip_padding, take_payload, verify};
+pub use warning::{Warning, WarningKind};
+
+/// State threaded through a parse: the options, and the warnings collected so
+/// far.
+struct Context<'a, 'o> {
+    options: &'o ParseOptions,
+    input: &'a [u8], // The whole input, for working out offsets
+    warnings: Vec<Warning>,
+    blocks: usize, // Blocks parsed so far, including nested ones
+}
+
+impl<'a, 'o> Context<'a, 'o> {
+    fn new(options: &'o ParseOptions, input: &'a [u8]) -> Self {
+        Context {
+            options,
+            input,
+            warnings: Vec::new(),
+            blocks: 0,
+        }
+    }
+}
+
+/// The result of the parsers that see `ParseOptions`.
+type ContextResult<'a, O> = IResult<&'a [u8], O, BlockError<&'a [u8]>>;
+
+/// Converts the error of a parser that doesn't see `ParseOptions`.
+fn lift<'a, O>(result: IResult<&'a [u8], O>) -> ContextResult<'a, O> {
+    result.map_err(nom::Err::convert)
+}
+
+/// Takes a container's payload together with the bytes padding it to a 4 byte
+/// boundary, which also pad its last child if the declared size stops short.
+fn take_container_payload(input: &[u8], data_length: usize) -> IResult<&[u8], &[u8]> {
+    let (rest, _payload) = take(data_length)(input)?;
+    let (rest, padding) = skip_padding(rest, data_length)?;
+
+    Ok((rest, &input[..data_length + padding.len()]))
+}
+
+fn container_size_count(input: &[u8]) -> IResult<&[u8], (usize, usize)> {
+    let (input, _data_type) = tag(&[0x0])(input)?;
+
+    parse_size_count(input)
+}
+
+fn parse_devc<'a>(context: &mut Context<'a, '_>, input: &'a [u8]) -> ContextResult<'a, Block> {
+    let (input, (size, count)) = lift(container_size_count(input))?;
+
+    let (input, block_bytes) = lift(take_container_payload(input, size * count))?;
+
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("DEVC", size = size * count).entered();
+
+    // Up to 3 bytes of padding may be left after the last child
+    let (_padding, sub_blocks) = parser(context, block_bytes)?;
+
+    Ok((input, Block::DeviceSource(sub_blocks)))
+}
+
+fn parse_dvid(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"L")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+    verify(input, count == 1)?;
+
+    let (input, device_id) = take(size * count)(input)?;
+
+    let mut device_id_array = [0u8; 4];
+    device_id_array.copy_from_slice(device_id);
+
+    Ok((input, Block::DeviceID(device_id_array)))
+}
+
+fn parse_dvnm(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"c")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let string_length = size * count;
+    let (input, device_name) = take(string_length)(input)?;
+    let device_name = decode_utf8(input, device_name)?;
+
+    // Take remaining padding bytes
+    let (input, _padding) = if string_length % 4 != 0 {
+        let count_remaining_bytes = 4 - string_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::DeviceName(device_name.to_string())))
+}
+
+fn parse_strm<'a>(context: &mut Context<'a, '_>, input: &'a [u8]) -> ContextResult<'a, Block> {
+    let (input, (size, count)) = lift(container_size_count(input))?;
+
+    let (input, block_bytes) = lift(take_container_payload(input, size * count))?;
+
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("STRM", size = size * count).entered();
+
+    // Up to 3 bytes of padding may be left after the last child
+    let (_padding, mut sub_blocks) = parser(context, block_bytes)?;
+
+    decode_complex(context, block_bytes, &mut sub_blocks);
+
+    Ok((input, Block::Stream(sub_blocks)))
+}
+
+/// Decodes the complex (`?`) blocks of a stream using its `TYPE`.
+///
+/// A `TYPE` only describes the blocks of its own stream, so this is applied to
+/// each stream's children separately. Blocks that don't fit the layout are
+/// left as `Custom`, and `FACE` records are turned into `Block::Faces`.
+fn decode_complex<'a>(context: &mut Context<'a, '_>, block_bytes: &'a [u8], blocks: &mut [Block]) {
+    // No record can be larger than the largest complex block
+    let max_record_size = blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::Custom(_, data) => Some(data.len()),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    let type_descriptor = blocks.iter().find_map(|block| match block {
+        Block::Type(descriptor) => {
+            TypeDescriptor::parse_bounded(descriptor.trim_end_matches('\0'), max_record_size).ok()
+        }
+        _ => None,
+    });
+    let type_descriptor = match type_descriptor {
+        Some(type_descriptor) => type_descriptor,
+        None => return untyped_complex(context, block_bytes, blocks),
+    };
+
+    let factors = scaling_factors(blocks);
+    for block in blocks.iter_mut() {
+        if let Block::Custom(type_name, data) = block {
+            if let Some(records) = type_descriptor.decode(data) {
+                let faces = match type_name.as_str() {
+                    "FACE" => faces_from_records(&records, &factors),
+                    _ => None,
+                };
+                *block = match faces {
+                    Some(faces) => Block::Faces(faces),
+                    None => Block::Complex(std::mem::take(type_name), records),
+                };
+            }
+        }
+    }
+}
+
+/// Without a usable `TYPE` there's no layout to decode complex blocks with, so
+/// each is kept as `Block::Unknown` with a `MissingType` warning.
+fn untyped_complex<'a>(context: &mut Context<'a, '_>, block_bytes: &'a [u8], blocks: &mut [Block]) {
+    // Find where each complex child starts, as the decoded blocks don't record it
+    let mut offsets = Vec::new();
+    let mut rest = block_bytes;
+    while let Ok((next, (header, _payload))) = take_raw_block(rest) {
+        if header.data_type == b'?' {
+            offsets.push((header.fourcc, context.input.offset(rest)));
+        }
+        rest = next;
+    }
+    let mut offsets = offsets.into_iter();
+
+    for block in blocks.iter_mut() {
+        if let Block::Custom(type_name, data) = block {
+            let (fourcc, offset) = offsets
+                .find(|(fourcc, _)| fourcc.0 == type_name.as_bytes())
+                .unwrap_or_else(|| (FourCC(*b"????"), context.input.offset(block_bytes)));
+
+            #[cfg(feature = "tracing")]
+            tracing::warn!(%fourcc, "complex block without a TYPE");
+
+            context.warnings.push(Warning {
+                fourcc,
+                offset,
+                kind: WarningKind::MissingType,
+            });
+            *block = Block::Unknown(std::mem::take(type_name), std::mem::take(data));
+        }
+    }
+}
+
+fn parse_stmp(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"J")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 8)?;
+    verify(input, count == 1)?;
+
+    let (input, start_timestamp) = be_u64(input)?;
+
+    Ok((input, Block::StartTimestamp(start_timestamp)))
+}
+
+fn parse_tsmp(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"L")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+    verify(input, count == 1)?;
+
+    let (input, total_samples) = be_u32(input)?;
+
+    Ok((input, Block::TotalSamples(total_samples)))
+}
+
+fn parse_stps(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"L")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+
+    let (input, offsets) = many_count(be_u32, count)(input)?;
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    Ok((input, Block::StreamTimeSync(offsets)))
+}
+
+fn parse_muid(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"L")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size.is_multiple_of(4))?;
+
+    // Written either as several u32s or as one record of all of them
+    let (input, uid) = many_count(be_u32, size * count / 4)(input)?;
+
+    Ok((input, Block::MediaUid(uid)))
+}
+
+fn parse_vers(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"B")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let data_length = size * count;
+    let (input, version) = take(data_length)(input)?;
+    let (input, _padding) = skip_padding(input, data_length)?;
+
+    Ok((input, Block::Version(version.to_vec())))
+}
+
+fn parse_stnm(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"c")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let string_length = size * count;
+    let (input, stream_name) = take(string_length)(input)?;
+    let stream_name = decode_utf8(input, stream_name)?;
+
+    // Take remaining padding bytes
+    let (input, _padding) = if string_length % 4 != 0 {
+        let count_remaining_bytes = 4 - string_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::StreamName(stream_name.to_string())))
+}
+
+fn parse_orin(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"c")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let string_length = size * count;
+    let (input, orientation) = take(string_length)(input)?;
+    let orientation = decode_utf8(input, orientation)?;
+
+    // Take remaining padding bytes
+    let (input, _padding) = if string_length % 4 != 0 {
+        let count_remaining_bytes = 4 - string_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::InputOrientation(orientation.to_string())))
+}
+
+fn parse_oren(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"c")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let string_length = size * count;
+    let (input, orientation) = take(string_length)(input)?;
+    let orientation = decode_utf8(input, orientation)?;
+    let (input, _padding) = skip_padding(input, string_length)?;
+
+    Ok((input, Block::OrientationSetting(orientation.to_string())))
+}
+
+fn parse_cmod(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, data_type) = alt((tag(b"B"), tag(b"S"), tag(b"L")))(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, count == 1)?;
+
+    // The width of the mode varies between firmware
+    let (input, mode) = if data_type == b"B" {
+        verify(input, size == 1)?;
+        let (input, mode) = be_u8(input)?;
+        (input, mode as u32)
+    } else if data_type == b"S" {
+        verify(input, size == 2)?;
+        let (input, mode) = be_u16(input)?;
+        (input, mode as u32)
+    } else {
+        verify(input, size == 4)?;
+        be_u32(input)?
+    };
+    let (input, _padding) = skip_padding(input, size)?;
+
+    Ok((input, Block::CaptureMode(CaptureMode::from(mode))))
+}
+
+fn parse_siun(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, units) = parse_units(input)?;
+    Ok((input, Block::UnitsSI(units)))
+}
+
+fn parse_unit(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, units) = parse_units(input)?;
+    Ok((input, Block::DisplayUnits(units)))
+}
+
+/// Decodes the units string shared by `SIUN` and `UNIT`.
+fn parse_units(input: &[u8]) -> IResult<&[u8], String> {
+    let (input, _data_type) = tag(b"c")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let string_length = size * count;
+    let (input, si_units) = take(string_length)(input)?;
+
+    let mut si_units = si_units.to_vec();
+    for byte in &mut si_units {
+        if *byte == 0xb2 {
+            // Seems to represent ^-2
+            *byte = b"2"[0];
+        }
+    }
+    let si_units = decode_utf8(input, &si_units)?;
+
+    // Take remaining padding bytes
+    let (input, _padding) = if string_length % 4 != 0 {
+        let count_remaining_bytes = 4 - string_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, si_units.to_string()))
+}
+
+fn parse_scal(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, data_type) = take(1usize)(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    if data_type == b"s" && count > 1 {
+        verify(input, size == 2)?;
+
+        // One divisor per axis, widened to match the `l` form
+        let (input, scaling_factors) = many_count(be_i16, count)(input)?;
+        let (input, _padding) = skip_padding(input, size * count)?;
+
+        let scaling_factors = scaling_factors.into_iter().map(i32::from).collect();
+        Ok((input, Block::ScalingFactorL(scaling_factors)))
+    } else if data_type == b"s" {
+        verify(input, size == 2)?;
+        verify(input, count == 1)?;
+
+        let (input, scaling_factor) = be_i16(input)?;
+
+        let (input, _) = take(2usize)(input)?;
+
+        Ok((input, Block::ScalingFactorS(scaling_factor)))
+    } else if data_type == b"l" {
+        verify(input, size == 4)?;
+
+        let mut input = input;
+        let mut scaling_factors = Vec::new();
+        for _ in 0..count {
+            let (iinput, scaling_factor) = be_i32(input)?;
+            scaling_factors.push(scaling_factor);
+            input = iinput; // TODO: tidy up
+        }
+
+        Ok((input, Block::ScalingFactorL(scaling_factors)))
+    } else {
+        Err(nom::Err::Failure(nom::error::Error::new(
+            data_type,
+            ErrorKind::Tag,
+        )))
+    }
+}
+
+fn parse_tmpc(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"f")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+    verify(input, count == 1)?;
+
+    let (input, temperature_celsius) = be_f32(input)?;
+
+    Ok((input, Block::Temperature(temperature_celsius)))
+}
+
+fn parse_timo(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"f")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+    verify(input, count == 1)?;
+
+    let (input, offset_seconds) = be_f32(input)?;
+
+    Ok((input, Block::TimeOffset(offset_seconds)))
+}
+
+/// Parses records of `N` big-endian `i16`s and their padding, for the sensor
+/// blocks that differ only in record width.
+fn parse_fixed_array<const N: usize>(data_type: u8, input: &[u8]) -> IResult<&[u8], Vec<[i16; N]>> {
+    let (input, _data_type) = tag(&[data_type][..])(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 2 * N)?; // Each measurement is N values
+
+    let (input, payload) = take(size * count)(input)?;
+    let measurements = payload
+        .chunks_exact(size)
+        .map(|record| {
+            let mut measurement = [0; N];
+            for (value, bytes) in measurement.iter_mut().zip(record.chunks_exact(2)) {
+                *value = i16::from_be_bytes([bytes[0], bytes[1]]);
+            }
+            measurement
+        })
+        .collect();
+
+    // Take remaining padding bytes
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    Ok((input, measurements))
+}
+
+/// Parses `count` values of `size` bytes and their padding, for the blocks
+/// whose data type varies by firmware. `size` must be the width of `T`.
+fn parse_values<'a, T>(
+    input: &'a [u8],
+    size: usize,
+    count: usize,
+    value: fn(&'a [u8]) -> IResult<&'a [u8], T>,
+) -> IResult<&'a [u8], Vec<T>> {
+    verify(input, size == std::mem::size_of::<T>())?;
+
+    let (input, values) = many_count(value, count)(input)?;
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    Ok((input, values))
+}
+
+fn parse_accl(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, measurements) = parse_fixed_array::<3>(b's', input)?;
+
+    Ok((input, Block::Acceleration(measurements)))
+}
+
+fn parse_gyro(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, measurements) = parse_fixed_array::<3>(b's', input)?;
+
+    Ok((input, Block::Gyroscope(measurements)))
+}
+
+fn parse_shut(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"f")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, shutter_speed) = be_f32(input)?;
+        measurements.push(shutter_speed);
+        input = iinput; // TODO: tidy up
+    }
+
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    Ok((input, Block::ShutterSpeed(measurements)))
+}
+
+fn parse_wbal(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, data_type) = alt((tag(b"S"), tag(b"s"), tag(b"f")))(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    // Most firmware stores colour temperatures as unsigned, but some use signed
+    // values or floats
+    if data_type == b"S" {
+        let (input, measurements) = parse_values(input, size, count, be_u16)?;
+        Ok((input, Block::WhiteBalance(measurements)))
+    } else if data_type == b"s" {
+        let (input, measurements) = parse_values(input, size, count, be_i16)?;
+        Ok((input, Block::WhiteBalanceSigned(measurements)))
+    } else {
+        let (input, measurements) = parse_values(input, size, count, be_f32)?;
+        Ok((input, Block::WhiteBalanceFloat(measurements)))
+    }
+}
+
+fn parse_wrgb(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"f")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 12)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, d1) = be_f32(input)?;
+        let (iinput, d2) = be_f32(iinput)?;
+        let (iinput, d3) = be_f32(iinput)?;
+        measurements.push([d1, d2, d3]);
+        input = iinput; // TODO: tidy up
+    }
+
+    // Take remaining padding bytes
+    let data_length = 12 * measurements.len();
+    let input = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?.0
+    } else {
+        input
+    };
+
+    Ok((input, Block::WhiteBalanceRGBGains(measurements)))
+}
+
+fn parse_isoe(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, data_type) = alt((tag(b"S"), tag(b"s")))(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    if data_type == b"S" {
+        let (input, measurements) = parse_values(input, size, count, be_u16)?;
+        Ok((input, Block::ISO(measurements)))
+    } else {
+        let (input, measurements) = parse_values(input, size, count, be_i16)?;
+        Ok((input, Block::ISOSigned(measurements)))
+    }
+}
+
+fn parse_unif(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"f")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, shutter_speed) = be_f32(input)?;
+        measurements.push(shutter_speed);
+        input = iinput; // TODO: tidy up
+    }
+
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    Ok((input, Block::ImageUniformity(measurements)))
+}
+
+fn parse_type(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"c")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let string_length = size * count;
+    let (input, stream_name) = take(string_length)(input)?;
+    let stream_name = decode_utf8(input, stream_name)?;
+
+    // Take remaining padding bytes
+    let (input, _padding) = if string_length % 4 != 0 {
+        let count_remaining_bytes = 4 - string_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::Type(stream_name.to_string())))
+}
+
+fn parse_registered<'a>(
+    decoder: &Decoder,
+    type_name: &'a [u8],
+    input: &'a [u8],
+) -> IResult<&'a [u8], Block> {
+    let type_name = decode_utf8(input, type_name)?;
+
+    let (input, _data_type) = take(1usize)(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let data_length = size * count;
+    let (input, data_bytes) = take(data_length)(input)?;
+    let (input, _padding) = skip_padding(input, data_length)?;
+
+    Ok((
+        input,
+        Block::Registered(type_name.to_string(), decoder(size, count, data_bytes)),
+    ))
+}
+
+/// Known single-value blocks, such as camera settings and counters, that need
+/// no dedicated parser and are labelled as `Block::Setting`.
+const SETTINGS: &[&[u8; 4]] = &[
+    b"AUDO", // Audio mode
+    b"AUPT", // Auto Protune
+    b"BROD", // Broadcast range
+    b"EISA", // Stabilisation
+    b"EISE", // Stabilisation enabled
+    b"EXPT", // Exposure type
+    b"GPSC", // GPS count
+    b"HSGT", // Hindsight
+    b"PIMN", // Minimum ISO
+    b"PIMX", // Maximum ISO
+    b"PRTN", // Protune
+    b"PTCL", // Protune colour
+    b"PTEV", // Protune EV compensation
+    b"PTSH", // Protune sharpness
+    b"PTWB", // Protune white balance
+    b"RATE", // Burst or time-lapse rate
+    b"SPED", // Speed ramp setting
+    b"VFOV", // Field of view
+    b"ZFOV", // Diagonal field of view in degrees
+];
+
+/// A generically decoded block, labelled as a setting if it's one of `SETTINGS`.
+fn generic_block(name: String, values: Values) -> Block {
+    if SETTINGS
+        .iter()
+        .any(|setting| setting[..] == *name.as_bytes())
+    {
+        Block::Setting(name, values)
+    } else {
+        Block::Generic(name, values)
+    }
+}
+
+/// Decodes a block without a dedicated parser from its data type alone.
+fn parse_generic<'a>(type_name: &'a [u8], input: &'a [u8]) -> IResult<&'a [u8], Block> {
+    let type_name = decode_utf8(input, type_name)?;
+
+    let (input, data_type) = one_of("LlfcsSbBjJd")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    // 16-bit records are grouped by their size, so it must hold whole values
+    if matches!(data_type, 's' | 'S') && (size == 0 || size % 2 != 0) {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            ErrorKind::Verify,
+        )));
+    }
+
+    let data_length = size * count;
+    let (input, payload) = take(data_length)(input)?;
+    let (input, _padding) = skip_padding(input, data_length)?;
+
+    let values = match data_type {
+        'L' => Values::U32(many_count(be_u32, data_length / 4)(payload)?.1),
+        'l' => Values::I32(many_count(be_i32, data_length / 4)(payload)?.1),
+        'f' => Values::F32(many_count(be_f32, data_length / 4)(payload)?.1),
+        'b' => Values::I8(many_count(be_i8, data_length)(payload)?.1),
+        'B' => Values::U8(payload.to_vec()),
+        'j' => Values::I64(many_count(be_i64, data_length / 8)(payload)?.1),
+        'J' => Values::U64(many_count(be_u64, data_length / 8)(payload)?.1),
+        'd' => Values::F64(many_count(be_f64, data_length / 8)(payload)?.1),
+        's' => Values::I16(group_16_bit(payload, size, i16::from_be_bytes)),
+        'S' => Values::U16(group_16_bit(payload, size, u16::from_be_bytes)),
+        // Strings are usually one record, but several records of `size`
+        // bytes are an array of fixed-width strings
+        'c' if size > 1 && count > 1 => {
+            Values::Strings(payload.chunks(size).map(decode_string).collect())
+        }
+        'c' => Values::String(decode_string(payload)),
+        _ => unreachable!(),
+    };
+
+    Ok((input, generic_block(type_name.to_string(), values)))
+}
+
+/// Splits `payload` into records of `size` bytes, each decoded as `size / 2`
+/// big-endian 16-bit values.
+fn group_16_bit<T>(payload: &[u8], size: usize, decode: fn([u8; 2]) -> T) -> Vec<Vec<T>> {
+    payload
+        .chunks(size)
+        .map(|record| {
+            record
+                .chunks_exact(2)
+                .map(|bytes| decode([bytes[0], bytes[1]]))
+                .collect()
+        })
+        .collect()
+}
+
+/// Decodes a string, dropping any NUL bytes padding it to a fixed width.
+fn decode_string(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes)
+        .trim_end_matches('\0')
+        .to_string()
+}
+
+fn parse_custom<'a>(type_name: &'a [u8], input: &'a [u8]) -> IResult<&'a [u8], Block> {
+    let type_name = decode_utf8(input, type_name)?;
+
+    let (input, _data_type) = tag(b"?")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let data_length = size * count;
+    let (input, data_bytes) = take(data_length)(input)?;
+    let input = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?.0
+    } else {
+        input
+    };
+
+    Ok((
+        input,
+        Block::Custom(type_name.to_string(), data_bytes.to_vec()),
+    ))
+}
+
+fn parse_gpsf(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"L")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+    verify(input, count == 1)?;
+
+    let (input, gpsf) = be_u32(input)?;
+
+    Ok((input, Block::GPSF(gpsf)))
+}
+
+fn parse_gpsu(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"U")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, count == 1)?;
+
+    let string_length = size * count;
+    let (input, gps_timestamp) = take(string_length)(input)?;
+    let gps_timestamp = decode_utf8(input, gps_timestamp)?;
+
+    // Take remaining padding bytes
+    let (input, _padding) = if string_length % 4 != 0 {
+        let count_remaining_bytes = 4 - string_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::GPSTimestamp(gps_timestamp.to_string())))
+}
+
+fn parse_gpsp(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"S")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 2)?;
+    verify(input, count == 1)?;
+
+    let (input, unknown) = be_u16(input)?;
+    let (input, _padding) = skip_padding(input, 2)?;
+
+    Ok((input, Block::GPSP(unknown)))
+}
+
+fn parse_gpsa(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"F")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 4)?;
+    verify(input, count == 1)?;
+
+    let (input, key) = take(4usize)(input)?;
+
+    Ok((input, Block::GPSA(AltitudeSystem::from(fourcc(key)))))
+}
+
+fn parse_gps5(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"l")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    // Each record is latitude, longitude, altitude, 2D speed and 3D speed,
+    // optionally followed by extra per-record fields on some firmware.
+    if size < 20 {
+        return Err(nom::Err::Failure(nom::error::Error::new(
+            input,
+            ErrorKind::LengthValue,
+        )));
+    }
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, latitude) = be_i32(input)?;
+        let (iinput, longitude) = be_i32(iinput)?;
+        let (iinput, altitude) = be_i32(iinput)?;
+        let (iinput, speed_2d) = be_i32(iinput)?;
+        let (iinput, speed_3d) = be_i32(iinput)?;
+        let (iinput, trailing) = take(size - 20)(iinput)?;
+        measurements.push((
+            [latitude, longitude, altitude, speed_2d, speed_3d],
+            trailing.to_vec(),
+        ));
+        input = iinput; // TODO: tidy up
+    }
+
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    Ok((input, Block::GPS5(measurements)))
+}
+
+fn parse_cori(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, measurements) = parse_fixed_array::<4>(b's', input)?;
+
+    Ok((input, Block::CameraOrientation(measurements)))
+}
+
+fn parse_iori(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, measurements) = parse_fixed_array::<4>(b's', input)?;
+
+    Ok((input, Block::ImageOrientation(measurements)))
+}
+
+fn parse_grav(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, measurements) = parse_fixed_array::<3>(b's', input)?;
+
+    Ok((input, Block::GravityVector(measurements)))
+}
+
+fn parse_mtrx(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"f")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size.is_multiple_of(4))?;
+
+    let (input, values) = many_count(be_f32, size / 4 * count)(input)?;
+    let (input, _padding) = skip_padding(input, size * count)?;
+
+    // The matrix dimension is implied by the number of floats in each record
+    let matrix = match size {
+        16 => Matrix::M2x2(
+            values
+                .chunks(4)
+                .map(|m| [[m[0], m[1]], [m[2], m[3]]])
+                .collect(),
+        ),
+        36 => Matrix::M3x3(
+            values
+                .chunks(9)
+                .map(|m| [[m[0], m[1], m[2]], [m[3], m[4], m[5]], [m[6], m[7], m[8]]])
+                .collect(),
+        ),
+        _ => Matrix::Other(values),
+    };
+
+    Ok((input, Block::Matrix(matrix)))
+}
+
+fn parse_wndm(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"B")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 2)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, enable) = be_u8(input)?;
+        let (iinput, meter_value) = be_u8(iinput)?;
+        measurements.push((enable, meter_value));
+        input = iinput; // TODO: tidy up
+    }
+
+    // Take remaining padding bytes
+    let data_length = size * count;
+    let (input, _padding) = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::WindProcessing(measurements)))
+}
+
+fn parse_mwet(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"B")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 3)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, mic_wet) = be_u8(input)?;
+        let (iinput, all_mics) = be_u8(iinput)?;
+        let (iinput, confidence) = be_u8(iinput)?;
+        measurements.push((mic_wet, all_mics, confidence));
+        input = iinput; // TODO: tidy up
+    }
+
+    // Take remaining padding bytes
+    let data_length = size * count;
+    let (input, _padding) = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::MicrophoneWet(measurements)))
+}
+
+fn parse_aalp(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"b")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 2)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, rms_level) = be_i8(input)?;
+        let (iinput, peak_level) = be_i8(iinput)?;
+        measurements.push((rms_level, peak_level));
+        input = iinput; // TODO: tidy up
+    }
+
+    // Take remaining padding bytes
+    let data_length = size * count;
+    let (input, _padding) = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::AGCAudioLevel(measurements)))
+}
+
+fn parse_mskp(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"s")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 2)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, skip) = be_i16(input)?;
+        measurements.push(skip);
+        input = iinput; // TODO: tidy up
+    }
+
+    // Take remaining padding bytes
+    let data_length = size * count;
+    let (input, _padding) = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::MRVFrameSkip(measurements)))
+}
+
+fn parse_lrvo(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"b")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 1)?;
+    verify(input, count == 1)?;
+
+    let (input, value) = be_i8(input)?;
+    let (input, _) = take(3usize)(input)?;
+
+    Ok((input, Block::LRVO(value)))
+}
+
+fn parse_lrvs(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"b")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 1)?;
+    verify(input, count == 1)?;
+
+    let (input, value) = be_i8(input)?;
+    let (input, _) = take(3usize)(input)?;
+
+    Ok((input, Block::LRVS(value)))
+}
+
+fn parse_lskp(input: &[u8]) -> IResult<&[u8], Block> {
+    let (input, _data_type) = tag(b"s")(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+    verify(input, size == 2)?;
+
+    let mut input = input;
+    let mut measurements = Vec::new();
+    for _ in 0..count {
+        let (iinput, skip) = be_i16(input)?;
+        measurements.push(skip);
+        input = iinput; // TODO: tidy up
+    }
+
+    // Take remaining padding bytes
+    let data_length = size * count;
+    let (input, _padding) = if data_length % 4 != 0 {
+        let count_remaining_bytes = 4 - data_length % 4;
+        take(count_remaining_bytes)(input)?
+    } else {
+        (input, &[][..])
+    };
+
+    Ok((input, Block::LRVFrameSkip(measurements)))
+}
+
+fn fourcc(block_type: &[u8]) -> FourCC {
+    FourCC(block_type.try_into().unwrap())
+}
+
+/// Parser for a block's type, size, count and payload, which follow its FourCC.
+pub type BlockParser = fn(&[u8]) -> IResult<&[u8], Block>;
+
+/// The dedicated parser for a FourCC, if there is one.
+///
+/// `DEVC` and `STRM` aren't included, since their children are parsed with the
+/// caller's `ParseOptions`.
+pub fn block_parser(fourcc: FourCC) -> Option<BlockParser> {
+    let parser: BlockParser = match &fourcc.0 {
+        b"DVID" => parse_dvid,
+        b"DVNM" => parse_dvnm,
+        b"STMP" => parse_stmp,
+        b"TSMP" => parse_tsmp,
+        b"STPS" => parse_stps,
+        b"STNM" => parse_stnm,
+        b"MUID" => parse_muid,
+        b"VERS" => parse_vers,
+        b"ORIN" => parse_orin,
+        b"OREN" => parse_oren,
+        b"CMOD" => parse_cmod,
+        b"SIUN" => parse_siun,
+        b"UNIT" => parse_unit,
+        b"SCAL" => parse_scal,
+        b"TMPC" => parse_tmpc,
+        b"TIMO" => parse_timo,
+        b"ACCL" => parse_accl,
+        b"GYRO" => parse_gyro,
+        b"SHUT" => parse_shut,
+        b"WBAL" => parse_wbal,
+        b"WRGB" => parse_wrgb,
+        b"ISOE" => parse_isoe,
+        b"UNIF" => parse_unif,
+        b"TYPE" => parse_type,
+        b"GPSF" => parse_gpsf,
+        b"GPSU" => parse_gpsu,
+        b"GPSP" => parse_gpsp,
+        b"GPSA" => parse_gpsa,
+        b"GPS5" => parse_gps5,
+        b"CORI" => parse_cori,
+        b"IORI" => parse_iori,
+        b"GRAV" => parse_grav,
+        b"MTRX" => parse_mtrx,
+        b"SMTR" => parse_mtrx,
+        b"WNDM" => parse_wndm,
+        b"MWET" => parse_mwet,
+        b"AALP" => parse_aalp,
+        b"MSKP" => parse_mskp,
+        b"LRVO" => parse_lrvo,
+        b"LRVS" => parse_lrvs,
+        b"LSKP" => parse_lskp,
+        _ => return None,
+    };
+
+    Some(parser)
+}
+
+fn parse_unknown<'a>(
+    context: &mut Context<'a, '_>,
+    block_type: &'a [u8],
+    input: &'a [u8],
+) -> IResult<&'a [u8], Block> {
+    let canonical = context.options.canonical(fourcc(block_type));
+    let r = match context.options.decoder(&canonical) {
+        Some(decoder) => parse_registered(decoder, block_type, input),
+        None => alt((
+            |input| parse_generic(block_type, input),
+            |input| parse_custom(block_type, input),
+        ))(input),
+    };
+    if r.is_err() {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            block_type = %String::from_utf8_lossy(block_type),
+            "unexpected block type"
+        );
+        Err(nom::Err::Failure(nom::error::Error::new(
+            input,
+            ErrorKind::Tag,
+        )))
+    } else {
+        r
+    }
+}
+
+fn parse_block<'a>(context: &mut Context<'a, '_>, input: &'a [u8]) -> ContextResult<'a, Block> {
+    context.blocks += 1;
+    if context.options.too_many_blocks(context.blocks) {
+        return Err(nom::Err::Failure(BlockError::TooManyBlocks(input)));
+    }
+
+    // Check the declared length and shape before taking the payload
+    if let Ok((_, header)) = parse_klv_header(input) {
+        if context.options.block_too_large(header.data_length()) {
+            return Err(nom::Err::Failure(BlockError::TooLarge(input)));
+        }
+
+        let fourcc = context.options.canonical(header.fourcc);
+        if (BlockHeader { fourcc, ..header }).validate().is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(%fourcc, size = header.size, "block size doesn't fit its type");
+            return Err(nom::Err::Failure(BlockError::InvalidSize(input)));
+        }
+    }
+
+    let (input, block_type) = lift(take(4usize)(input))?;
+    let canonical = context.options.canonical(fourcc(block_type));
+    let (input, block) = match &canonical.0 {
+        b"DEVC" => parse_devc(context, input),
+        b"STRM" => parse_strm(context, input),
+        _ => lift(match block_parser(canonical) {
+            Some(parser) => parser(input),
+            None => parse_unknown(context, block_type, input),
+        }),
+    }?;
+
+    #[cfg(feature = "tracing")]
+    tracing::debug!(block_type = %String::from_utf8_lossy(block_type), ?block, "decoded block");
+
+    Ok((input, block))
+}
+
+/// Parses the first block in `input`, including any nested children.
+pub fn parse_one(input: &[u8]) -> IResult<&[u8], Block> {
+    parse_one_with(input, &ParseOptions::default())
+}
+
+/// Like `parse_one`, but decoding unknown FourCCs with any decoders registered
+/// in `options`. When lenient, only the block's children are recovered and
+/// their warnings are discarded.
+pub fn parse_one_with<'a>(input: &'a [u8], options: &ParseOptions) -> IResult<&'a [u8], Block> {
+    parse_one_limited(input, options).map_err(nom::Err::convert)
+}
+
+/// Like `parse_one_with`, but keeping the limits in `options` apart from
+/// nom's errors.
+fn parse_one_limited<'a>(input: &'a [u8], options: &ParseOptions) -> ContextResult<'a, Block> {
+    parse_block(&mut Context::new(options, input), input)
+}
+
+/// Skips over a block that failed to parse using its KLV header, recording a
+/// warning. Strings that failed to decode are recovered lossily and blocks
+/// with unknown FourCCs are kept as `Block::Unknown`, but blocks that don't
+/// match their FourCC are dropped.
+///
+/// If the header or payload can't be read either, the size is assumed to be
+/// corrupt and parsing resumes at the next `DEVC`.
+fn recover<'a>(
+    context: &mut Context<'a, '_>,
+    input: &'a [u8],
+    err: nom::Err<BlockError<&'a [u8]>>,
+) -> ContextResult<'a, Option<Block>> {
+    let (rest, (header, payload)) = match take_raw_block(input) {
+        Ok(raw_block) => raw_block,
+        Err(_) => return Ok(resync(context, input)),
+    };
+
+    let name = String::from_utf8_lossy(&header.fourcc.0).into_owned();
+    let canonical = context.options.canonical(header.fourcc);
+    let known = matches!(&canonical.0, b"DEVC" | b"STRM")
+        || block_parser(canonical).is_some()
+        || context.options.decoder(&canonical).is_some();
+    let invalid_utf8 = matches!(
+        &err,
+        nom::Err::Error(BlockError::Nom(error)) | nom::Err::Failure(BlockError::Nom(error))
+            if error.code == ErrorKind::Char
+    );
+
+    let (kind, block) = if invalid_utf8 {
+        let block = lossy_string_block(&header, name, payload);
+        (WarningKind::InvalidUtf8, Some(block))
+    } else if known {
+        (WarningKind::Malformed, None)
+    } else {
+        (
+            WarningKind::UnknownBlock,
+            Some(Block::Unknown(name, payload.to_vec())),
+        )
+    };
+
+    #[cfg(feature = "tracing")]
+    tracing::warn!(fourcc = %header.fourcc, ?kind, "recovered from bad block");
+
+    context.warnings.push(Warning {
+        fourcc: header.fourcc,
+        offset: context.input.offset(input),
+        kind,
+    });
+
+    Ok((rest, block))
+}
+
+/// Whether parsing stopped at one of the limits in `ParseOptions`, which
+/// lenient parsing doesn't recover from.
+fn is_over_limit(err: &nom::Err<BlockError<&[u8]>>) -> bool {
+    match err {
+        nom::Err::Error(error) | nom::Err::Failure(error) => match error {
+            BlockError::TooLarge(_) | BlockError::TooManyBlocks(_) => true,
+            BlockError::Nom(_) | BlockError::InvalidSize(_) => false,
+        },
+        nom::Err::Incomplete(_) => false,
+    }
+}
+
+/// Skips to the next `DEVC` key on a 4 byte boundary, or to the end of `input`
+/// if there isn't one, recording how many bytes were skipped.
+fn resync<'a>(context: &mut Context<'a, '_>, input: &'a [u8]) -> (&'a [u8], Option<Block>) {
+    let skipped = (4..input.len())
+        .step_by(4)
+        .find(|&start| input[start..].starts_with(b"DEVC"))
+        .unwrap_or(input.len());
+
+    let fourcc = fourcc(&input[..4]);
+    #[cfg(feature = "tracing")]
+    tracing::warn!(%fourcc, skipped, "skipped to the next DEVC");
+
+    context.warnings.push(Warning {
+        fourcc,
+        offset: context.input.offset(input),
+        kind: WarningKind::Resynced { skipped },
+    });
+
+    (&input[skipped..], None)
+}
+
+fn take_raw_block(input: &[u8]) -> IResult<&[u8], (BlockHeader, &[u8])> {
+    let (input, header) = parse_klv_header(input)?;
+    let (input, payload) = take_payload(input, &header)?;
+    let (input, _padding) = skip_padding(input, header.data_length())?;
+
+    Ok((input, (header, payload)))
+}
+
+/// Rebuilds a string block whose payload wasn't valid UTF-8, decoding it
+/// lossily.
+fn lossy_string_block(header: &BlockHeader, name: String, payload: &[u8]) -> Block {
+    let string = String::from_utf8_lossy(payload).into_owned();
+    match &header.fourcc.0 {
+        b"DVNM" => Block::DeviceName(string),
+        b"STNM" => Block::StreamName(string),
+        b"ORIN" => Block::InputOrientation(string),
+        b"OREN" => Block::OrientationSetting(string),
+        b"SIUN" => Block::UnitsSI(string),
+        b"UNIT" => Block::DisplayUnits(string),
+        b"TYPE" => Block::Type(string),
+        b"GPSU" => Block::GPSTimestamp(string),
+        _ if header.data_type == b'c' => {
+            generic_block(name, Values::String(decode_string(payload)))
+        }
+        _ => Block::Unknown(name, payload.to_vec()),
+    }
+}
+
+fn parser<'a>(context: &mut Context<'a, '_>, input: &'a [u8]) -> ContextResult<'a, Vec<Block>> {
+    let mut blocks = Vec::new();
+    let mut input = input;
+    // Anything shorter than a FourCC can only be padding
+    while input.len() >= 4 {
+        match parse_block(context, input) {
+            Ok(result) => {
+                input = result.0;
+                if let Block::DeviceSource(_) = result.1 {
+                    let consumed = context.input.offset(input);
+                    context
+                        .options
+                        .report_progress(consumed, Some(context.input.len()));
+                }
+                blocks.push(result.1);
+            }
+            Err(err) if context.options.is_lenient() && !is_over_limit(&err) => {
+                let result = recover(context, input, err)?;
+                input = result.0;
+                blocks.extend(result.1);
+            }
+            Err(err) => return Err(err),
+        }
+    }
+    Ok((input, blocks))
+}
+
+// TODO: streaming
+// pub fn parse_metadata<T: Read>(mut f: T) -> Result<Vec<u8>, ParseError> {
+//     let mut buffer = [0; 10];
+//     let bytes_read = f.read(&mut buffer)?;
+
+//     let mut input = &buffer[..bytes_read];
+//     loop {
+//         let result = parser(input);
+
+//         if let Ok((new_input, _x)) = result {
+//             input = new_input;
+//         }
+
+//         if let Err(nom::Err::Incomplete(_)) = result {
+//             let bytes_read = f.read(&mut buffer)?;
+//             input = &buffer[..bytes_read];
+//             continue;
+//         }
+
+//         result?;
+//     }
+
+//     // Ok(vec![])
+// }
+
+pub fn parse_slice(input: &[u8]) -> Result<Vec<Block>, ParseError> {
+    let (blocks, _warnings) = parse_slice_with(input, &ParseOptions::default())?;
+
+    Ok(blocks)
+}
+
+/// Parses `input` with `options`, also returning the warnings for any blocks
+/// that were recovered. Blocks that fail to parse are only recovered when
+/// `options` is lenient, but complex blocks without a `TYPE` are always kept
+/// as `Block::Unknown` with a warning.
+///
+/// Input shorter than a block header, including an empty file, has no blocks.
+pub fn parse_slice_with(
+    input: &[u8],
+    options: &ParseOptions,
+) -> Result<(Vec<Block>, Vec<Warning>), ParseError> {
+    // Too short for even a header, so there can't be any blocks
+    if input.len() < 8 {
+        return Ok((Vec::new(), Vec::new()));
+    }
+
+    let mut context = Context::new(options, input);
+    let (trailing_bytes, result) = parser(&mut context, input)?;
+    verify(trailing_bytes, trailing_bytes.is_empty())?;
+
+    Ok((result, context.warnings))
+}
+
+pub fn parse_metadata<T: Read>(f: T) -> Result<Vec<Block>, ParseError> {
+    let (blocks, _warnings) = parse_metadata_with(f, &ParseOptions::default())?;
+
+    Ok(blocks)
+}
+
+pub fn parse_metadata_with<T: Read>(
+    mut f: T,
+    options: &ParseOptions,
+) -> Result<(Vec<Block>, Vec<Warning>), ParseError> {
+    let mut buffer = Vec::new();
+    let bytes_read = f.read_to_end(&mut buffer)?;
+
+    parse_slice_with(&buffer[..bytes_read], options)
+}
+
+/// Parses a file by memory-mapping it rather than reading it into memory.
+#[cfg(feature = "memmap2")]
+pub fn parse_mmap<P: AsRef<std::path::Path>>(path: P) -> Result<Vec<Block>, ParseError> {
+    let file = std::fs::File::open(path)?;
+    // Safety: the mapping is only read while parsing, but it is undefined
+    // behaviour if the file is modified by another process in the meantime.
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+
+    parse_slice(&mmap)
+}
+
+/// Parses the top-level blocks of `input` in parallel. Each block is found from
+/// its header alone, then decoded on its own thread.
+#[cfg(feature = "rayon")]
+pub fn parse_parallel(input: &[u8]) -> Result<Vec<Block>, ParseError> {
+    use rayon::prelude::*;
+
+    if input.len() < 8 {
+        return Ok(Vec::new());
+    }
+
+    let mut slices = Vec::new();
+    let mut rest = input;
+    while !rest.is_empty() {
+        let (next, _raw_block) = take_raw_block(rest)?;
+        slices.push(&rest[..rest.len() - next.len()]);
+        rest = next;
+    }
+
+    let blocks = slices
+        .par_iter()
+        .map(|slice| parse_slice(slice))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(blocks.into_iter().flatten().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::type_descriptor::ComplexValue;
+    use util::{container, klv};
+
+    #[test]
+    fn parses_signed_white_balance_and_iso() {
+        let input = [
+            klv(b"WBAL", b's', 2, 2, &[0x13, 0x88, 0xff, 0xff]),
+            klv(b"ISOE", b's', 2, 1, &[0x00, 0x64]),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::WhiteBalanceSigned(vec![5000, -1]),
+                Block::ISOSigned(vec![100]),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_trailing_gps5_fields_in_step() {
+        // Three records of five i32s, each followed by a quality byte
+        let record = |n: u8, quality: u8| {
+            let mut record: Vec<u8> = (0..5).flat_map(|field| [0, 0, 0, n + field]).collect();
+            record.push(quality);
+            record
+        };
+        let records = [record(10, 1), record(20, 2), record(30, 3)].concat();
+        let input = [
+            klv(b"GPS5", b'l', 21, 3, &records),
+            klv(b"GPSF", b'L', 4, 1, &[0, 0, 0, 3]),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::GPS5(vec![
+                    ([10, 11, 12, 13, 14], vec![1]),
+                    ([20, 21, 22, 23, 24], vec![2]),
+                    ([30, 31, 32, 33, 34], vec![3]),
+                ]),
+                Block::GPSF(3),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_blocks_after_odd_count_orientations() {
+        let quaternions: Vec<u8> = (1..=12)
+            .flat_map(|value: i16| value.to_be_bytes())
+            .collect();
+        let input = [
+            klv(b"CORI", b's', 8, 3, &quaternions),
+            klv(b"IORI", b's', 8, 1, &quaternions[..8]),
+            klv(b"TSMP", b'L', 4, 1, &[0, 0, 0, 4]),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::CameraOrientation(vec![[1, 2, 3, 4], [5, 6, 7, 8], [9, 10, 11, 12]]),
+                Block::ImageOrientation(vec![[1, 2, 3, 4]]),
+                Block::TotalSamples(4),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_fixed_width_string_arrays() {
+        let input = [
+            klv(b"XSTR", b'c', 8, 1, b"one\0two\0"),
+            klv(b"XSTA", b'c', 4, 2, b"one\0two\0"),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::Generic("XSTR".to_string(), Values::String("one\0two".to_string())),
+                Block::Generic(
+                    "XSTA".to_string(),
+                    Values::Strings(vec!["one".to_string(), "two".to_string()])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_non_zero_gpsp_padding() {
+        let mut gpsp = klv(b"GPSP", b'S', 2, 1, &[0x01, 0xf4]);
+        gpsp[10..12].copy_from_slice(&[0xab, 0xcd]);
+        let input = [gpsp, klv(b"GPSF", b'L', 4, 1, &[0, 0, 0, 3])].concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![Block::GPSP(500), Block::GPSF(3)]
+        );
+    }
+
+    #[test]
+    fn parses_unsigned_and_float_white_balance() {
+        let input = [
+            klv(b"WBAL", b'S', 2, 1, &[0x13, 0x88]),
+            klv(
+                b"WBAL",
+                b'f',
+                4,
+                2,
+                &[0x45, 0x9c, 0x40, 0x00, 0x45, 0xbb, 0x80, 0x00],
+            ),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::WhiteBalance(vec![5000]),
+                Block::WhiteBalanceFloat(vec![5000.0, 6000.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn scopes_type_to_its_stream() {
+        let record = [0x00, 0x01, 0x00, 0x02];
+        let input = container(
+            b"DEVC",
+            &[
+                container(
+                    b"STRM",
+                    &[
+                        klv(b"TYPE", b'c', 1, 1, b"L"),
+                        klv(b"XCPX", b'?', 4, 1, &record),
+                    ],
+                ),
+                container(
+                    b"STRM",
+                    &[
+                        klv(b"TYPE", b'c', 2, 1, b"ss"),
+                        klv(b"XCPX", b'?', 4, 1, &record),
+                    ],
+                ),
+            ],
+        );
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![Block::DeviceSource(vec![
+                Block::Stream(vec![
+                    Block::Type("L".to_string()),
+                    Block::Complex("XCPX".to_string(), vec![vec![ComplexValue::U32(0x10002)]]),
+                ]),
+                Block::Stream(vec![
+                    Block::Type("ss".to_string()),
+                    Block::Complex(
+                        "XCPX".to_string(),
+                        vec![vec![ComplexValue::I16(1), ComplexValue::I16(2)]]
+                    ),
+                ]),
+            ])]
+        );
+    }
+
+    #[test]
+    fn accepts_containers_excluding_their_last_childs_padding() {
+        // The STRM's size covers the name but not the byte padding it out
+        let mut stream = container(b"STRM", &[klv(b"STNM", b'c', 3, 1, b"abc")]);
+        stream[7] = 11;
+        assert_eq!(
+            parse_slice(&stream).unwrap(),
+            vec![Block::Stream(vec![Block::StreamName("abc".to_string())])]
+        );
+    }
+
+    #[test]
+    fn keeps_complex_blocks_without_a_type_as_unknown() {
+        let input = container(
+            b"STRM",
+            &[
+                klv(b"XCPX", b'?', 4, 1, &[1, 2, 3, 4]),
+                klv(b"STNM", b'c', 4, 1, b"Test"),
+            ],
+        );
+        let (blocks, warnings) = parse_slice_with(&input, &ParseOptions::default()).unwrap();
+        assert_eq!(
+            blocks,
+            vec![Block::Stream(vec![
+                Block::Unknown("XCPX".to_string(), vec![1, 2, 3, 4]),
+                Block::StreamName("Test".to_string()),
+            ])]
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                fourcc: FourCC(*b"XCPX"),
+                offset: 8,
+                kind: WarningKind::MissingType,
+            }]
+        );
+    }
+
+    #[test]
+    fn groups_16_bit_values_by_record_size() {
+        let values: Vec<u8> = (1..=10)
+            .flat_map(|value: i16| value.to_be_bytes())
+            .collect();
+        let input = [
+            klv(b"XPRS", b's', 4, 2, &values[..8]),
+            klv(b"XFVS", b'S', 10, 2, &values),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::Generic(
+                    "XPRS".to_string(),
+                    Values::I16(vec![vec![1, 2], vec![3, 4]])
+                ),
+                Block::Generic(
+                    "XFVS".to_string(),
+                    Values::U16(vec![vec![1, 2, 3, 4, 5], vec![6, 7, 8, 9, 10]])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_a_version_before_the_first_devc() {
+        let input = [
+            klv(b"VERS", b'B', 1, 3, &[1, 1, 0]),
+            container(b"DEVC", &[klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 1])]),
+        ]
+        .concat();
+        let blocks = parse_slice(&input).unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Version(vec![1, 1, 0]),
+                Block::DeviceSource(vec![Block::DeviceID([0, 0, 0, 1])]),
+            ]
+        );
+        assert_eq!(crate::block::gpmf_version(&blocks), Some(&[1, 1, 0][..]));
+    }
+
+    #[test]
+    fn inputs_shorter_than_a_header_have_no_blocks() {
+        let bytes = *b"DEVC\0\x01\0";
+        for length in [0, 3, 7] {
+            let input = &bytes[..length];
+            assert_eq!(parse_slice(input).unwrap(), vec![]);
+            assert_eq!(parse_metadata(input).unwrap(), vec![]);
+        }
+    }
+
+    #[test]
+    fn decodes_unknown_well_typed_blocks_generically() {
+        let input = [
+            klv(b"XI8S", b'b', 1, 2, &[0xff, 0x02]),
+            klv(b"XU64", b'J', 8, 1, &7u64.to_be_bytes()),
+            klv(b"XF64", b'd', 8, 1, &1.5f64.to_be_bytes()),
+            klv(b"ZFOV", b'f', 4, 1, &120.0f32.to_be_bytes()),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::Generic("XI8S".to_string(), Values::I8(vec![-1, 2])),
+                Block::Generic("XU64".to_string(), Values::U64(vec![7])),
+                Block::Generic("XF64".to_string(), Values::F64(vec![1.5])),
+                Block::Setting("ZFOV".to_string(), Values::F32(vec![120.0])),
+            ]
+        );
+    }
+
+    #[test]
+    fn recovers_from_corrupt_blocks_only_when_lenient() {
+        let input = container(
+            b"DEVC",
+            &[
+                klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 1]),
+                klv(b"TMPC", b'L', 4, 1, &[0, 0, 0, 0]),
+                klv(b"DVNM", b'c', 1, 2, &[0xff, b'A']),
+                klv(b"TMPC", b'f', 4, 1, &40.5f32.to_be_bytes()),
+            ],
+        );
+
+        assert!(matches!(parse_slice(&input), Err(ParseError::Generic)));
+
+        let mut options = ParseOptions::new();
+        options.lenient(true);
+        let (blocks, warnings) = parse_slice_with(&input, &options).unwrap();
+        assert_eq!(
+            blocks,
+            vec![Block::DeviceSource(vec![
+                Block::DeviceID([0, 0, 0, 1]),
+                Block::DeviceName("\u{fffd}A".to_string()),
+                Block::Temperature(40.5),
+            ])]
+        );
+        assert_eq!(
+            warnings,
+            vec![
+                Warning {
+                    fourcc: FourCC(*b"TMPC"),
+                    offset: 20,
+                    kind: WarningKind::Malformed,
+                },
+                Warning {
+                    fourcc: FourCC(*b"DVNM"),
+                    offset: 32,
+                    kind: WarningKind::InvalidUtf8,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn resyncs_at_the_next_devc_after_a_corrupt_size() {
+        let mut corrupt = container(b"DEVC", &[klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 1])]);
+        // Claim far more data than the input holds
+        corrupt[6..8].copy_from_slice(&0x1000u16.to_be_bytes());
+        let valid = container(b"DEVC", &[klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 2])]);
+        let input = [corrupt.clone(), valid].concat();
+
+        let mut options = ParseOptions::new();
+        options.lenient(true);
+        let (blocks, warnings) = parse_slice_with(&input, &options).unwrap();
+        assert_eq!(
+            blocks,
+            vec![Block::DeviceSource(vec![Block::DeviceID([0, 0, 0, 2])])]
+        );
+        assert_eq!(
+            warnings,
+            vec![Warning {
+                fourcc: FourCC(*b"DEVC"),
+                offset: 0,
+                kind: WarningKind::Resynced {
+                    skipped: corrupt.len(),
+                },
+            }]
+        );
+    }
+
+    #[test]
+    fn rejects_blocks_over_the_size_limit_even_when_lenient() {
+        let input = container(
+            b"DEVC",
+            &[
+                klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 1]),
+                klv(b"DVNM", b'c', 1, 9, b"Camera 10"),
+            ],
+        );
+
+        let mut options = ParseOptions::new();
+        options.max_block_bytes(input.len() - 8);
+        assert!(parse_slice_with(&input, &options).is_ok());
+
+        options.max_block_bytes(8);
+        assert!(matches!(
+            parse_slice_with(&input, &options),
+            Err(ParseError::BlockTooLarge)
+        ));
+        options.lenient(true);
+        assert!(matches!(
+            parse_slice_with(&input, &options),
+            Err(ParseError::BlockTooLarge)
+        ));
+
+        let mut state = ParseState::with_options(options);
+        assert!(matches!(
+            parse_incremental(&mut state, &input),
+            Err(ParseError::BlockTooLarge)
+        ));
+    }
+
+    #[test]
+    fn stops_after_the_maximum_number_of_blocks() {
+        let input = container(
+            b"DEVC",
+            &[
+                klv(b"DVID", b'L', 4, 1, &[0, 0, 0, 1]),
+                klv(b"TMPC", b'f', 4, 1, &40.5f32.to_be_bytes()),
+            ],
+        );
+
+        // The DEVC counts as well as its children
+        let mut options = ParseOptions::new();
+        options.max_blocks(3);
+        assert!(parse_slice_with(&input, &options).is_ok());
+
+        options.max_blocks(2).lenient(true);
+        assert!(matches!(
+            parse_slice_with(&input, &options),
+            Err(ParseError::TooManyBlocks)
+        ));
+        assert!(matches!(
+            parse_incremental(&mut ParseState::with_options(options), &input),
+            Err(ParseError::TooManyBlocks)
+        ));
+    }
+
+    fn two_devices() -> Vec<u8> {
+        let device = |id: u8, name: &[u8]| {
+            container(
+                b"DEVC",
+                &[
+                    klv(b"DVID", b'L', 4, 1, &[0, 0, 0, id]),
+                    klv(b"DVNM", b'c', 1, name.len() as u16, name),
+                    container(
+                        b"STRM",
+                        &[
+                            klv(b"STNM", b'c', 1, 11, b"Temperature"),
+                            klv(b"TMPC", b'f', 4, 1, &40.5f32.to_be_bytes()),
+                        ],
+                    ),
+                ],
+            )
+        };
+        [device(1, b"Camera"), device(2, b"GPS")].concat()
+    }
+
+    #[test]
+    fn parses_chunks_split_anywhere_like_the_whole_input() {
+        let input = two_devices();
+        let expected = parse_slice(&input).unwrap();
+        assert_eq!(expected.len(), 2);
+
+        // Chunks of 1 to 7 bytes split headers as well as payloads
+        for chunk_size in 1..=input.len() {
+            let mut state = ParseState::new();
+            let mut blocks = Vec::new();
+            for chunk in input.chunks(chunk_size) {
+                blocks.extend(parse_incremental(&mut state, chunk).unwrap());
+            }
+            assert_eq!(blocks, expected, "chunks of {} bytes", chunk_size);
+            assert_eq!(state.buffered(), 0);
+        }
+
+        // A single split in the middle of the second DEVC's header
+        let split = input.len() / 2 + 3;
+        let mut state = ParseState::new();
+        let first = parse_incremental(&mut state, &input[..split]).unwrap();
+        let second = parse_incremental(&mut state, &input[split..]).unwrap();
+        assert_eq!(first, expected[..1]);
+        assert_eq!(second, expected[1..]);
+    }
+
+    #[test]
+    fn reads_blocks_larger_than_the_buffer() {
+        let input = two_devices();
+        let mut options = ParseOptions::new();
+        options.buffer_size(5);
+
+        let blocks = BlockReader::with_options(&input[..], options)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(blocks, parse_slice(&input).unwrap());
+    }
+
+    #[test]
+    fn decodes_unknown_blocks_with_registered_decoders() {
+        #[derive(Debug, PartialEq)]
+        struct Gain(u32);
+
+        let mut options = ParseOptions::new();
+        options.register(FourCC(*b"XGAN"), |size, count, data| {
+            assert_eq!((size, count), (4, 1));
+            Gain(u32::from_be_bytes(data.try_into().unwrap()))
+        });
+        let input = [
+            klv(b"XGAN", b'L', 4, 1, &7u32.to_be_bytes()),
+            klv(b"XOTH", b'L', 4, 1, &7u32.to_be_bytes()),
+        ]
+        .concat();
+
+        let (blocks, warnings) = parse_slice_with(&input, &options).unwrap();
+        assert_eq!(warnings, vec![]);
+        let value = match &blocks[0] {
+            Block::Registered(name, value) => {
+                assert_eq!(name, "XGAN");
+                value
+            }
+            block => panic!("expected a registered block, got {:?}", block),
+        };
+        assert_eq!(value.downcast_ref::<Gain>(), Some(&Gain(7)));
+        assert_eq!(value.downcast_ref::<u32>(), None);
+        // Other unknown blocks are still decoded generically
+        assert_eq!(
+            blocks[1],
+            Block::Generic("XOTH".to_string(), Values::U32(vec![7]))
+        );
+
+        // Values compare by identity rather than by content
+        assert_eq!(blocks[0], blocks[0].clone());
+        let (reparsed, _) = parse_slice_with(&input, &options).unwrap();
+        assert_ne!(blocks[0], reparsed[0]);
+    }
+
+    #[test]
+    fn decodes_aliases_as_their_target() {
+        let payload = [0, 1, 0, 2, 0xff, 0xfd];
+        let input = [
+            klv(b"XACC", b's', 6, 1, &payload),
+            klv(b"ACCL", b's', 6, 1, &payload),
+        ]
+        .concat();
+        let acceleration = Block::Acceleration(vec![[1, 2, -3]]);
+
+        let mut options = ParseOptions::new();
+        options.alias(FourCC(*b"XACC"), FourCC(*b"ACCL"));
+        let (blocks, _warnings) = parse_slice_with(&input, &options).unwrap();
+        assert_eq!(blocks, vec![acceleration.clone(), acceleration.clone()]);
+
+        // Without the alias, the block isn't known to be acceleration
+        let blocks = parse_slice(&input).unwrap();
+        assert_eq!(
+            blocks[0],
+            Block::Generic("XACC".to_string(), Values::I16(vec![vec![1, 2, -3]]))
+        );
+        assert_eq!(blocks[1], acceleration);
+    }
+
+    #[test]
+    fn rejects_sizes_that_dont_fit_the_type() {
+        let xi32 = klv(b"XI32", b'l', 6, 1, &[0, 0, 0, 1, 0, 0]);
+        let inputs = [
+            // Whole values, but not a whole ACCL record
+            (klv(b"ACCL", b's', 4, 1, &[0, 1, 0, 2]), None),
+            // Half an i16
+            (klv(b"ACCL", b's', 5, 1, &[0, 1, 0, 2, 0]), None),
+            // Too short for a GPS5 record
+            (klv(b"GPS5", b'l', 16, 1, &[0; 16]), None),
+            // Half an i32, for a FourCC without a dedicated parser
+            (
+                xi32.clone(),
+                Some(Block::Unknown("XI32".to_string(), xi32[8..14].to_vec())),
+            ),
+        ];
+        for (input, recovered) in inputs {
+            assert!(
+                matches!(parse_slice(&input), Err(ParseError::InvalidSize)),
+                "{:?}",
+                &input[..8]
+            );
+
+            // Known FourCCs are dropped and unknown ones kept raw
+            let mut options = ParseOptions::new();
+            options.lenient(true);
+            let (blocks, warnings) = parse_slice_with(&input, &options).unwrap();
+            let kind = match recovered {
+                Some(_) => WarningKind::UnknownBlock,
+                None => WarningKind::Malformed,
+            };
+            assert_eq!(blocks, recovered.into_iter().collect::<Vec<_>>());
+            assert_eq!(warnings[0].kind, kind);
+        }
+
+        let input = klv(b"ACCL", b's', 6, 1, &[0, 1, 0, 2, 0, 3]);
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![Block::Acceleration(vec![[1, 2, 3]])]
+        );
+    }
+
+    #[test]
+    fn shapes_matrices_by_record_size() {
+        let floats = |values: &[f32]| -> Vec<u8> {
+            values
+                .iter()
+                .flat_map(|value| value.to_be_bytes())
+                .collect()
+        };
+        let identity_2x2 = [1.0, 0.0, 0.0, 1.0];
+        let identity_3x3 = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        let input = [
+            klv(
+                b"MTRX",
+                b'f',
+                16,
+                2,
+                &floats(&[identity_2x2, [2.0; 4]].concat()),
+            ),
+            klv(b"SMTR", b'f', 36, 1, &floats(&identity_3x3)),
+            klv(b"MTRX", b'f', 12, 1, &floats(&[1.0, 2.0, 3.0])),
+            klv(b"TMPC", b'f', 4, 1, &floats(&[40.5])),
+        ]
+        .concat();
+
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::Matrix(Matrix::M2x2(vec![
+                    [[1.0, 0.0], [0.0, 1.0]],
+                    [[2.0, 2.0], [2.0, 2.0]],
+                ])),
+                Block::Matrix(Matrix::M3x3(vec![[
+                    [1.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0],
+                    [0.0, 0.0, 1.0],
+                ]])),
+                Block::Matrix(Matrix::Other(vec![1.0, 2.0, 3.0])),
+                Block::Temperature(40.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_stream_time_sync_offsets() {
+        let offsets: Vec<u8> = [4096u32, 8192, 12288]
+            .iter()
+            .flat_map(|offset| offset.to_be_bytes())
+            .collect();
+        let input = [
+            klv(b"STPS", b'L', 4, 3, &offsets),
+            klv(b"TSMP", b'L', 4, 1, &400u32.to_be_bytes()),
+        ]
+        .concat();
+        assert_eq!(
+            parse_slice(&input).unwrap(),
+            vec![
+                Block::StreamTimeSync(vec![4096, 8192, 12288]),
+                Block::TotalSamples(400),
+            ]
+        );
+
+        let input = klv(b"STPS", b'L', 4, 3, &offsets[..8]);
+        assert!(parse_slice(&input).is_err());
+    }
+}
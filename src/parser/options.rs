@@ -0,0 +1,174 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+use std::sync::{Arc, Mutex};
+
+use crate::fourcc::FourCC;
+
+/// A value produced by a decoder registered with [`ParseOptions::register`].
+pub trait CustomValue: Any + Debug + Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl dyn CustomValue {
+    /// The decoded value, if it has type `T`.
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.as_any().downcast_ref()
+    }
+}
+
+/// Registered values are opaque, so they're only equal to clones of the same
+/// value: two blocks decoded separately from identical bytes compare unequal.
+/// Compare the values themselves with `downcast_ref` instead.
+impl PartialEq for dyn CustomValue {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(
+            self as *const dyn CustomValue as *const u8,
+            other as *const dyn CustomValue as *const u8,
+        )
+    }
+}
+
+impl<T: Any + Debug + Send + Sync> CustomValue for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// Bytes read at a time by `BlockReader` unless set with
+/// [`ParseOptions::buffer_size`].
+const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+pub(crate) type Decoder = dyn Fn(usize, usize, &[u8]) -> Arc<dyn CustomValue> + Send + Sync;
+
+type Progress = dyn FnMut(usize, Option<usize>) + Send;
+
+/// Settings controlling how metadata is parsed.
+#[derive(Default)]
+pub struct ParseOptions {
+    decoders: HashMap<FourCC, Box<Decoder>>,
+    aliases: HashMap<FourCC, FourCC>,
+    lenient: bool,
+    max_block_bytes: Option<usize>,
+    max_blocks: Option<usize>,
+    buffer_size: Option<usize>,
+    progress: Option<Mutex<Box<Progress>>>,
+}
+
+impl ParseOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a decoder for blocks with an otherwise unknown FourCC.
+    ///
+    /// The decoder is given the block's size, count and payload (without
+    /// padding), and its result is returned as `Block::Registered`.
+    pub fn register<T, F>(&mut self, fourcc: FourCC, decoder: F) -> &mut Self
+    where
+        T: CustomValue,
+        F: Fn(usize, usize, &[u8]) -> T + Send + Sync + 'static,
+    {
+        self.decoders.insert(
+            fourcc,
+            Box::new(move |size, count, data| Arc::new(decoder(size, count, data))),
+        );
+        self
+    }
+
+    /// Decodes blocks with FourCC `alias` as if they were `fourcc`, for cameras
+    /// that use their own codes for standard data, e.g. `XACC` for `ACCL`.
+    pub fn alias(&mut self, alias: FourCC, fourcc: FourCC) -> &mut Self {
+        self.aliases.insert(alias, fourcc);
+        self
+    }
+
+    pub(crate) fn canonical(&self, fourcc: FourCC) -> FourCC {
+        self.aliases.get(&fourcc).copied().unwrap_or(fourcc)
+    }
+
+    /// Skips or preserves blocks that fail to parse rather than failing the
+    /// whole parse, reporting each one as a `Warning`.
+    pub fn lenient(&mut self, lenient: bool) -> &mut Self {
+        self.lenient = lenient;
+        self
+    }
+
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Rejects any block whose declared payload is longer than `max` bytes
+    /// with `ParseError::BlockTooLarge`, even when lenient.
+    pub fn max_block_bytes(&mut self, max: usize) -> &mut Self {
+        self.max_block_bytes = Some(max);
+        self
+    }
+
+    pub(crate) fn block_too_large(&self, data_length: usize) -> bool {
+        self.max_block_bytes.is_some_and(|max| data_length > max)
+    }
+
+    /// Stops with `ParseError::TooManyBlocks` once a parse has seen more than
+    /// `max` blocks, counting nested ones, to bound the work done on untrusted
+    /// input. Unlimited by default. Each top-level block counts as a parse of
+    /// its own with `parse_incremental` and `BlockReader`.
+    pub fn max_blocks(&mut self, max: usize) -> &mut Self {
+        self.max_blocks = Some(max);
+        self
+    }
+
+    pub(crate) fn too_many_blocks(&self, blocks: usize) -> bool {
+        self.max_blocks.is_some_and(|max| blocks > max)
+    }
+
+    /// Reads `bytes` at a time when streaming with `BlockReader`, 64 KiB by
+    /// default. Smaller reads return blocks sooner, while larger reads make
+    /// fewer calls on bulk files.
+    pub fn buffer_size(&mut self, bytes: usize) -> &mut Self {
+        self.buffer_size = Some(bytes.max(1));
+        self
+    }
+
+    pub(crate) fn read_buffer_size(&self) -> usize {
+        self.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE)
+    }
+
+    /// Calls `progress` after each top-level `DEVC` is parsed, with the number
+    /// of bytes consumed so far and the total length of the input. The total
+    /// is `None` when streaming with `parse_incremental` or `BlockReader`.
+    pub fn progress<F>(&mut self, progress: F) -> &mut Self
+    where
+        F: FnMut(usize, Option<usize>) + Send + 'static,
+    {
+        self.progress = Some(Mutex::new(Box::new(progress)));
+        self
+    }
+
+    pub(crate) fn report_progress(&self, consumed: usize, total: Option<usize>) {
+        if let Some(progress) = &self.progress {
+            // A callback that panicked before won't be called again
+            if let Ok(mut progress) = progress.lock() {
+                progress(consumed, total);
+            }
+        }
+    }
+
+    pub(crate) fn decoder(&self, fourcc: &FourCC) -> Option<&Decoder> {
+        self.decoders.get(fourcc).map(Box::as_ref)
+    }
+}
+
+impl Debug for ParseOptions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("decoders", &self.decoders.keys().collect::<Vec<_>>())
+            .field("aliases", &self.aliases)
+            .field("lenient", &self.lenient)
+            .field("max_block_bytes", &self.max_block_bytes)
+            .field("max_blocks", &self.max_blocks)
+            .field("buffer_size", &self.read_buffer_size())
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
@@ -1,5 +1,10 @@
+use nom::bytes::streaming::take;
+use nom::error::{Error, ErrorKind};
 use nom::number::streaming::{be_u16, be_u8};
-use nom::IResult;
+use nom::{Err, IResult};
+
+use crate::fourcc::FourCC;
+use crate::header::BlockHeader;
 
 pub fn parse_size_count(input: &[u8]) -> IResult<&[u8], (usize, usize)> {
     let (input, size) = be_u8(input)?;
@@ -7,3 +12,77 @@ pub fn parse_size_count(input: &[u8]) -> IResult<&[u8], (usize, usize)> {
 
     Ok((input, (size as usize, count as usize)))
 }
+
+pub fn parse_klv_header(input: &[u8]) -> IResult<&[u8], BlockHeader> {
+    let (input, fourcc) = take(4usize)(input)?;
+    let (input, data_type) = be_u8(input)?;
+    let (input, (size, count)) = parse_size_count(input)?;
+
+    let mut fourcc_array = [0u8; 4];
+    fourcc_array.copy_from_slice(fourcc);
+
+    Ok((
+        input,
+        BlockHeader {
+            fourcc: FourCC(fourcc_array),
+            data_type,
+            size,
+            count,
+        },
+    ))
+}
+
+/// Takes the payload described by `header`, excluding padding.
+pub fn take_payload<'a>(input: &'a [u8], header: &BlockHeader) -> IResult<&'a [u8], &'a [u8]> {
+    take(header.data_length())(input)
+}
+
+/// Skips the bytes padding a payload of `data_length` bytes to a 4 byte boundary.
+pub fn skip_padding(input: &[u8], data_length: usize) -> IResult<&[u8], &[u8]> {
+    match data_length % 4 {
+        0 => Ok((input, &[][..])),
+        remainder => take(4 - remainder)(input),
+    }
+}
+
+/// Fails with `ErrorKind::Verify` unless `condition` holds, for checking a
+/// block's size and count against what its parser expects.
+pub fn verify(input: &[u8], condition: bool) -> Result<(), Err<Error<&[u8]>>> {
+    if condition {
+        Ok(())
+    } else {
+        Err(Err::Failure(Error::new(input, ErrorKind::Verify)))
+    }
+}
+
+/// Decodes a string, failing with `ErrorKind::Char` if it isn't valid UTF-8.
+pub fn decode_utf8<'a, 'b>(
+    input: &'a [u8],
+    bytes: &'b [u8],
+) -> Result<&'b str, Err<Error<&'a [u8]>>> {
+    std::str::from_utf8(bytes).map_err(|_| Err::Failure(Error::new(input, ErrorKind::Char)))
+}
+
+/// Encodes a block, padding `payload` to a multiple of 4 bytes with zeros.
+#[cfg(test)]
+pub(crate) fn klv(
+    fourcc: &[u8; 4],
+    data_type: u8,
+    size: u8,
+    count: u16,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut block = fourcc.to_vec();
+    block.push(data_type);
+    block.push(size);
+    block.extend_from_slice(&count.to_be_bytes());
+    block.extend_from_slice(payload);
+    block.resize(block.len() + (4 - payload.len() % 4) % 4, 0);
+    block
+}
+
+#[cfg(test)]
+pub(crate) fn container(fourcc: &[u8; 4], children: &[Vec<u8>]) -> Vec<u8> {
+    let payload = children.concat();
+    klv(fourcc, 0, 1, payload.len() as u16, &payload)
+}
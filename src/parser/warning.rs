@@ -0,0 +1,27 @@
+use crate::fourcc::FourCC;
+
+/// A block that couldn't be decoded normally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub fourcc: FourCC,
+    pub offset: usize, // Of the block's FourCC, in bytes from the start of the input
+    pub kind: WarningKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A string wasn't valid UTF-8, so it was decoded lossily.
+    InvalidUtf8,
+    /// The FourCC isn't recognised and its data type can't be decoded, so the
+    /// block was kept as `Block::Unknown`.
+    UnknownBlock,
+    /// The data type, size or count didn't match what the FourCC should hold,
+    /// so the block was skipped.
+    Malformed,
+    /// The block's size ran past the end of its container or the input, so
+    /// `skipped` bytes were skipped to reach the next `DEVC`.
+    Resynced { skipped: usize },
+    /// A complex (`?`) block's stream has no usable `TYPE`, so the block was
+    /// kept as `Block::Unknown`. Reported even when not parsing leniently.
+    MissingType,
+}
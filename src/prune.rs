@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use crate::block::{descendants, Block};
+
+/// Removes whole streams until the encoded size of `blocks` is at most
+/// `max_bytes`, dropping the streams with the highest sample rate first.
+pub fn prune_to_size(blocks: Vec<Block>, max_bytes: usize) -> Vec<Block> {
+    prune_to_size_by(blocks, max_bytes, |_name, samples| {
+        std::cmp::Reverse(samples)
+    })
+}
+
+/// Removes whole streams until the encoded size of `blocks` is at most
+/// `max_bytes`, dropping the lowest priority streams first.
+///
+/// Streams are identified by their `STNM` across every payload, with unnamed
+/// streams treated as one stream named `""`. `priority` is given each stream's
+/// name and its largest sample count in any one payload.
+///
+/// If removing every stream still doesn't meet the budget, all of them are
+/// removed and the remaining blocks are returned as they are.
+pub fn prune_to_size_by<F, K>(blocks: Vec<Block>, max_bytes: usize, priority: F) -> Vec<Block>
+where
+    F: Fn(&str, usize) -> K,
+    K: Ord,
+{
+    let mut encoded_len: usize = blocks.iter().map(Block::encoded_len).sum();
+    if encoded_len <= max_bytes {
+        return blocks;
+    }
+
+    // Total encoded size and peak sample count of each stream
+    let mut streams: HashMap<&str, (usize, usize)> = HashMap::new();
+    for block in descendants(&blocks) {
+        if let Block::Stream(children) = block {
+            let samples = children.iter().map(Block::sample_count).max().unwrap_or(0);
+            let stream = streams.entry(stream_name(children)).or_insert((0, 0));
+            stream.0 += block.encoded_len();
+            stream.1 = stream.1.max(samples);
+        }
+    }
+
+    let mut streams: Vec<(&str, (usize, usize))> = streams.into_iter().collect();
+    streams.sort_by_key(|&(name, (_, samples))| priority(name, samples));
+
+    let mut removed = Vec::new();
+    for (name, (stream_len, _)) in streams {
+        if encoded_len <= max_bytes {
+            break;
+        }
+        encoded_len -= stream_len;
+        removed.push(name.to_string());
+    }
+
+    remove_streams(blocks, &removed)
+}
+
+fn stream_name(children: &[Block]) -> &str {
+    children
+        .iter()
+        .find_map(|child| match child {
+            Block::StreamName(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .unwrap_or("")
+}
+
+fn remove_streams(blocks: Vec<Block>, removed: &[String]) -> Vec<Block> {
+    blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Stream(children) => {
+                let name = stream_name(&children);
+                if removed.iter().any(|removed| removed == name) {
+                    None
+                } else {
+                    Some(Block::Stream(remove_streams(children, removed)))
+                }
+            }
+            Block::DeviceSource(children) => {
+                Some(Block::DeviceSource(remove_streams(children, removed)))
+            }
+            block => Some(block),
+        })
+        .collect()
+}
@@ -0,0 +1,97 @@
+use crate::block::{Block, DeviceId};
+use crate::devc::Devc;
+use crate::fourcc::FourCC;
+use crate::scaling::{apply_scaling, scaling_factors};
+use crate::timing::{offset_sample_times, time_offset};
+
+/// One sample of one stream, in a flat "long" layout ready for a data frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub device: Option<DeviceId>,
+    pub stream: Option<String>, // STNM
+    pub fourcc: FourCC,
+    pub sample_index: usize,    // Within the stream, counting across payloads
+    pub timestamp: Option<f64>, // Seconds, if every payload of the stream has a STMP
+    pub values: Vec<f64>,       // Scaled, one per component
+}
+
+/// A stream's scaled samples from each of its payloads.
+struct StreamSamples {
+    device: Option<DeviceId>,
+    stream: Option<String>,
+    fourcc: FourCC,
+    payloads: Vec<(Option<u64>, f64, usize, Vec<f64>)>, // STMP, TIMO, components and values
+}
+
+/// Every numeric sample of every stream in the top-level `DEVC` blocks, with
+/// `SCAL` applied and timestamps from `sample_times`, shifted by any `TIMO`.
+///
+/// Streams are matched across payloads by device, name and FourCC. Records are
+/// grouped by stream, in the order each stream first appears.
+pub fn to_long_records(blocks: &[Block]) -> Vec<Record> {
+    let mut streams: Vec<StreamSamples> = Vec::new();
+    for devc in blocks.iter().filter_map(Devc::new) {
+        let device = devc.device_id();
+        for stream in devc.streams() {
+            let children = stream.children();
+            let (fourcc, samples) = match children
+                .iter()
+                .find_map(|child| Some((child.fourcc(), child.samples()?)))
+            {
+                Some(samples) => samples,
+                None => continue,
+            };
+
+            let (components, mut values) = samples.to_f64();
+            apply_scaling(&mut values, components, &scaling_factors(children));
+            let payload = (
+                stream.start_timestamp(),
+                time_offset(children),
+                components,
+                values,
+            );
+
+            let name = stream.name().map(str::to_string);
+            match streams.iter_mut().find(|existing| {
+                existing.device == device && existing.stream == name && existing.fourcc == fourcc
+            }) {
+                Some(existing) => existing.payloads.push(payload),
+                None => streams.push(StreamSamples {
+                    device,
+                    stream: name,
+                    fourcc,
+                    payloads: vec![payload],
+                }),
+            }
+        }
+    }
+
+    let mut records = Vec::new();
+    for stream in streams {
+        let starts: Option<Vec<(u64, usize, f64)>> = stream
+            .payloads
+            .iter()
+            .map(|(start, offset, components, values)| {
+                Some(((*start)?, values.len() / components, *offset))
+            })
+            .collect();
+        let times = starts.map(|starts| offset_sample_times(&starts));
+
+        let samples = stream
+            .payloads
+            .iter()
+            .flat_map(|(_, _, components, values)| values.chunks(*components));
+        for (sample_index, values) in samples.enumerate() {
+            records.push(Record {
+                device: stream.device,
+                stream: stream.stream.clone(),
+                fourcc: stream.fourcc,
+                sample_index,
+                timestamp: times.as_ref().map(|times| times[sample_index]),
+                values: values.to_vec(),
+            });
+        }
+    }
+
+    records
+}
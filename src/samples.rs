@@ -0,0 +1,129 @@
+use crate::block::{Block, Values};
+
+/// The numeric payload of a block, grouped by sample shape rather than by
+/// FourCC.
+#[derive(Debug)]
+pub enum Samples {
+    I16(Vec<i16>),
+    U16(Vec<u16>),
+    I32(Vec<i32>),
+    U32(Vec<u32>),
+    F32(Vec<f32>),
+    Triplet(Vec<[i16; 3]>),
+    Quartet(Vec<[i16; 4]>),
+    I32Quintet(Vec<[i32; 5]>),
+    F32Triplet(Vec<[f32; 3]>),
+    I8Pair(Vec<[i8; 2]>),
+    U8Pair(Vec<[u8; 2]>),
+    U8Triplet(Vec<[u8; 3]>),
+    I16Records(Vec<Vec<i16>>), // Of any width, all the same
+    U16Records(Vec<Vec<u16>>),
+    I8(Vec<i8>),
+    U8(Vec<u8>),
+    I64(Vec<i64>),
+    U64(Vec<u64>),
+    F64(Vec<f64>),
+}
+
+impl Samples {
+    /// Row-major values widened to `f64`, along with the number of components
+    /// in each sample.
+    pub fn to_f64(&self) -> (usize, Vec<f64>) {
+        fn widen<T: Copy + Into<f64>, const N: usize>(samples: &[[T; N]]) -> (usize, Vec<f64>) {
+            let values = samples
+                .iter()
+                .flatten()
+                .map(|&value| value.into())
+                .collect();
+            (N, values)
+        }
+
+        fn widen_records<T: Copy + Into<f64>>(samples: &[Vec<T>]) -> (usize, Vec<f64>) {
+            let width = samples.first().map_or(1, Vec::len);
+            let values = samples
+                .iter()
+                .flatten()
+                .map(|&value| value.into())
+                .collect();
+            (width, values)
+        }
+
+        fn widen_scalars<T: Copy + Into<f64>>(samples: &[T]) -> (usize, Vec<f64>) {
+            (1, samples.iter().map(|&value| value.into()).collect())
+        }
+
+        match self {
+            Samples::I16(samples) => widen_scalars(samples),
+            Samples::U16(samples) => widen_scalars(samples),
+            Samples::I32(samples) => widen_scalars(samples),
+            Samples::U32(samples) => widen_scalars(samples),
+            Samples::F32(samples) => widen_scalars(samples),
+            Samples::Triplet(samples) => widen(samples),
+            Samples::Quartet(samples) => widen(samples),
+            Samples::I32Quintet(samples) => widen(samples),
+            Samples::F32Triplet(samples) => widen(samples),
+            Samples::I8Pair(samples) => widen(samples),
+            Samples::U8Pair(samples) => widen(samples),
+            Samples::U8Triplet(samples) => widen(samples),
+            Samples::I16Records(samples) => widen_records(samples),
+            Samples::U16Records(samples) => widen_records(samples),
+            Samples::I8(samples) => widen_scalars(samples),
+            Samples::U8(samples) => widen_scalars(samples),
+            // 64-bit integers may lose precision
+            Samples::I64(samples) => (1, samples.iter().map(|&value| value as f64).collect()),
+            Samples::U64(samples) => (1, samples.iter().map(|&value| value as f64).collect()),
+            Samples::F64(samples) => widen_scalars(samples),
+        }
+    }
+}
+
+impl Block {
+    /// The block's numeric samples, or `None` for containers, strings,
+    /// scaling factors and other non-sample blocks.
+    pub fn samples(&self) -> Option<Samples> {
+        let samples = match self {
+            Block::Acceleration(measurements)
+            | Block::Gyroscope(measurements)
+            | Block::GravityVector(measurements) => Samples::Triplet(measurements.clone()),
+            Block::CameraOrientation(measurements) | Block::ImageOrientation(measurements) => {
+                Samples::Quartet(measurements.clone())
+            }
+            Block::ShutterSpeed(measurements)
+            | Block::ImageUniformity(measurements)
+            | Block::WhiteBalanceFloat(measurements) => Samples::F32(measurements.clone()),
+            Block::WhiteBalance(measurements) | Block::ISO(measurements) => {
+                Samples::U16(measurements.clone())
+            }
+            Block::WhiteBalanceSigned(measurements)
+            | Block::ISOSigned(measurements)
+            | Block::MRVFrameSkip(measurements)
+            | Block::LRVFrameSkip(measurements) => Samples::I16(measurements.clone()),
+            Block::WhiteBalanceRGBGains(measurements) => Samples::F32Triplet(measurements.clone()),
+            Block::GPS5(measurements) => {
+                Samples::I32Quintet(measurements.iter().map(|(values, _)| *values).collect())
+            }
+            Block::WindProcessing(measurements) => {
+                Samples::U8Pair(measurements.iter().map(|&(a, b)| [a, b]).collect())
+            }
+            Block::MicrophoneWet(measurements) => {
+                Samples::U8Triplet(measurements.iter().map(|&(a, b, c)| [a, b, c]).collect())
+            }
+            Block::AGCAudioLevel(measurements) => {
+                Samples::I8Pair(measurements.iter().map(|&(a, b)| [a, b]).collect())
+            }
+            Block::Generic(_, Values::U32(values)) => Samples::U32(values.clone()),
+            Block::Generic(_, Values::I32(values)) => Samples::I32(values.clone()),
+            Block::Generic(_, Values::F32(values)) => Samples::F32(values.clone()),
+            Block::Generic(_, Values::I16(records)) => Samples::I16Records(records.clone()),
+            Block::Generic(_, Values::U16(records)) => Samples::U16Records(records.clone()),
+            Block::Generic(_, Values::I8(values)) => Samples::I8(values.clone()),
+            Block::Generic(_, Values::U8(values)) => Samples::U8(values.clone()),
+            Block::Generic(_, Values::I64(values)) => Samples::I64(values.clone()),
+            Block::Generic(_, Values::U64(values)) => Samples::U64(values.clone()),
+            Block::Generic(_, Values::F64(values)) => Samples::F64(values.clone()),
+            _ => return None,
+        };
+
+        Some(samples)
+    }
+}
@@ -0,0 +1,177 @@
+use crate::block::Block;
+
+/// The `SCAL` divisors among a stream's children, or a divisor of 1 if the
+/// stream has no `SCAL`.
+pub fn scaling_factors(children: &[Block]) -> Vec<f64> {
+    children
+        .iter()
+        .find_map(|child| match child {
+            Block::ScalingFactorS(factor) => Some(vec![*factor as f64]),
+            Block::ScalingFactorL(factors) => {
+                Some(factors.iter().map(|&factor| factor as f64).collect())
+            }
+            _ => None,
+        })
+        .unwrap_or_else(|| vec![1.0])
+}
+
+/// Divides row-major samples of `components` values each by the scaling
+/// factors.
+///
+/// When there is one factor per component they are applied per axis, as for a
+/// `SCAL` with a divisor for each of `ACCL`'s three axes. Otherwise the first
+/// factor is applied to every value.
+pub fn apply_scaling(values: &mut [f64], components: usize, factors: &[f64]) {
+    let per_axis = factors.len() == components;
+    let factor = factors.first().copied().unwrap_or(1.0);
+    for sample in values.chunks_mut(components) {
+        for (axis, value) in sample.iter_mut().enumerate() {
+            *value /= if per_axis { factors[axis] } else { factor };
+        }
+    }
+}
+
+/// The rate of change of scaled three-axis samples, such as jerk from `ACCL`
+/// or angular acceleration from `GYRO`, in units per second.
+///
+/// `timestamps` are in seconds, one per sample, but needn't be evenly spaced.
+/// If the lengths differ, only as many samples as have timestamps are used.
+/// Interior samples use a central difference over their neighbours, and the
+/// first and last use a one-sided difference. Intervals with no elapsed time
+/// give zero, as does a single sample.
+pub fn differentiate(samples: &[[f64; 3]], timestamps: &[f64]) -> Vec<[f64; 3]> {
+    let len = samples.len().min(timestamps.len());
+    let (samples, timestamps) = (&samples[..len], &timestamps[..len]);
+
+    let last = samples.len().saturating_sub(1);
+    (0..samples.len())
+        .map(|index| {
+            let (before, after) = (index.saturating_sub(1), (index + 1).min(last));
+            let elapsed = timestamps[after] - timestamps[before];
+            let mut derivative = [0.0; 3];
+            if elapsed > 0.0 {
+                for (axis, value) in derivative.iter_mut().enumerate() {
+                    *value = (samples[after][axis] - samples[before][axis]) / elapsed;
+                }
+            }
+            derivative
+        })
+        .collect()
+}
+
+/// A scaled `GYRO` sample, in radians per second about each axis.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GyroSample(pub [f64; 3]);
+
+impl GyroSample {
+    /// The angular velocity about each axis in degrees per second.
+    pub fn deg_per_s(&self) -> [f64; 3] {
+        self.0.map(f64::to_degrees)
+    }
+}
+
+/// Scaled `GYRO` samples converted from radians to degrees per second.
+pub fn degrees_per_second(samples: &[[f64; 3]]) -> Vec<[f64; 3]> {
+    samples
+        .iter()
+        .map(|&sample| GyroSample(sample).deg_per_s())
+        .collect()
+}
+
+/// The rotation about each axis since the first sample, from integrating
+/// scaled angular velocity with the trapezoidal rule, in the units of
+/// `samples` multiplied by seconds.
+///
+/// `timestamps` are in seconds, one per sample, but needn't be evenly spaced.
+/// If the lengths differ, only as many samples as have timestamps are used.
+/// Each axis is integrated on its own, which only
+/// matches Euler angles while the rotation stays small, and any bias in the
+/// gyroscope accumulates, so the angles drift further the longer the
+/// recording.
+pub fn integrate_orientation(samples: &[[f64; 3]], timestamps: &[f64]) -> Vec<[f64; 3]> {
+    let len = samples.len().min(timestamps.len());
+    let (samples, timestamps) = (&samples[..len], &timestamps[..len]);
+
+    let mut angle = [0.0; 3];
+    let mut angles = Vec::with_capacity(samples.len());
+    for index in 0..samples.len() {
+        if index > 0 {
+            let elapsed = timestamps[index] - timestamps[index - 1];
+            for (axis, value) in angle.iter_mut().enumerate() {
+                *value += (samples[index - 1][axis] + samples[index][axis]) / 2.0 * elapsed;
+            }
+        }
+        angles.push(angle);
+    }
+
+    angles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_slice;
+    use crate::parser::util::{container, klv};
+    use crate::stream::Stream;
+
+    #[test]
+    fn differentiates_unevenly_spaced_samples() {
+        let samples = [
+            [0.0, 0.0, 1.0],
+            [1.0, 0.0, 1.0],
+            [4.0, 0.0, 1.0],
+            [9.0, 0.0, 1.0],
+        ];
+        let timestamps = [0.0, 1.0, 2.0, 4.0];
+        assert_eq!(
+            differentiate(&samples, &timestamps),
+            vec![
+                [1.0, 0.0, 0.0],       // One-sided, over the first interval
+                [2.0, 0.0, 0.0],       // Central, over two seconds
+                [8.0 / 3.0, 0.0, 0.0], // Central, over three seconds
+                [2.5, 0.0, 0.0],       // One-sided, over the last interval
+            ]
+        );
+    }
+
+    #[test]
+    fn differentiates_only_timed_samples() {
+        let samples = [[0.0; 3], [1.0; 3], [2.0; 3]];
+        assert_eq!(
+            differentiate(&samples, &[0.0, 1.0]),
+            vec![[1.0; 3], [1.0; 3]]
+        );
+        assert_eq!(differentiate(&samples[..1], &[0.0, 1.0]), vec![[0.0; 3]]);
+    }
+
+    #[test]
+    fn converts_gyro_samples_to_degrees() {
+        let sample = GyroSample([std::f64::consts::PI, 0.0, -std::f64::consts::FRAC_PI_2]);
+        assert_eq!(sample.deg_per_s(), [180.0, 0.0, -90.0]);
+    }
+
+    #[test]
+    fn integrates_unevenly_spaced_samples() {
+        let samples = [[0.0, 1.0, 0.0], [2.0, 1.0, 0.0], [2.0, 1.0, 0.0]];
+        let timestamps = [0.0, 1.0, 3.0];
+        assert_eq!(
+            integrate_orientation(&samples, &timestamps),
+            vec![[0.0, 0.0, 0.0], [1.0, 1.0, 0.0], [5.0, 3.0, 0.0]]
+        );
+        assert_eq!(integrate_orientation(&samples, &timestamps[..2]).len(), 2);
+    }
+
+    #[test]
+    fn scales_each_axis_by_its_own_factor() {
+        let input = container(
+            b"STRM",
+            &[
+                klv(b"SCAL", b's', 2, 3, &[0, 1, 0, 2, 0, 4]),
+                klv(b"ACCL", b's', 6, 1, &[0, 8, 0, 8, 0, 8]),
+            ],
+        );
+        let blocks = parse_slice(&input).unwrap();
+        let stream = Stream::new(&blocks[0]).unwrap();
+        assert_eq!(stream.as_soa(), Some(vec![vec![8.0], vec![4.0], vec![2.0]]));
+    }
+}
@@ -0,0 +1,201 @@
+use crate::block::Block;
+use crate::fourcc::FourCC;
+use crate::missing::MissingBlock;
+use crate::samples::Samples;
+use crate::scaling::{apply_scaling, scaling_factors};
+
+/// What a stream measures, from its `STNM`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamKind {
+    Accelerometer,
+    Gyroscope,
+    Magnetometer,
+    Gps,
+    GravityVector,
+    CameraOrientation,
+    ImageOrientation,
+    ShutterSpeed,
+    WhiteBalance,
+    WhiteBalanceGains,
+    Iso,
+    ImageUniformity,
+    Faces,
+    Other(String), // The name as written
+}
+
+/// The start of each `STNM` the cameras write, many of which go on to list
+/// the axes or fields, e.g. "GPS (Lat., Long., Alt., 2D speed, 3D speed)".
+const STREAM_KINDS: &[(&str, StreamKind)] = &[
+    ("Accelerometer", StreamKind::Accelerometer),
+    ("Gyroscope", StreamKind::Gyroscope),
+    ("Magnetometer", StreamKind::Magnetometer),
+    ("GPS", StreamKind::Gps),
+    ("Gravity Vector", StreamKind::GravityVector),
+    ("CameraOrientation", StreamKind::CameraOrientation),
+    ("ImageOrientation", StreamKind::ImageOrientation),
+    ("Exposure time", StreamKind::ShutterSpeed),
+    ("White Balance temperature", StreamKind::WhiteBalance),
+    ("White Balance RGB gains", StreamKind::WhiteBalanceGains),
+    ("Sensor ISO", StreamKind::Iso),
+    ("Image uniformity", StreamKind::ImageUniformity),
+    ("Face", StreamKind::Faces),
+];
+
+/// A typed view over the children of a `STRM` block.
+#[derive(Debug, Clone, Copy)]
+pub struct Stream<'a> {
+    children: &'a [Block],
+}
+
+impl<'a> Stream<'a> {
+    /// Views `block` as a stream, or returns `None` if it isn't a `STRM`.
+    pub fn new(block: &'a Block) -> Option<Self> {
+        match block {
+            Block::Stream(children) => Some(Stream { children }),
+            _ => None,
+        }
+    }
+
+    pub fn children(&self) -> &'a [Block] {
+        self.children
+    }
+
+    /// The stream name from `STNM`.
+    pub fn name(&self) -> Option<&'a str> {
+        self.children.iter().find_map(|child| match child {
+            Block::StreamName(name) => Some(name.as_str()),
+            _ => None,
+        })
+    }
+
+    /// What the stream measures, recognised from its name, or `None` if it has
+    /// no `STNM`.
+    pub fn kind(&self) -> Option<StreamKind> {
+        let name = self.name()?;
+        let kind = STREAM_KINDS
+            .iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+            .map_or_else(
+                || StreamKind::Other(name.to_string()),
+                |(_, kind)| kind.clone(),
+            );
+
+        Some(kind)
+    }
+
+    /// The start of the stream's samples in microseconds, from a `STMP` inside
+    /// the `STRM`, as opposed to the payload's own `Devc::start_timestamp`.
+    pub fn start_timestamp(&self) -> Option<u64> {
+        self.children.iter().find_map(|child| match child {
+            Block::StartTimestamp(start) => Some(*start),
+            _ => None,
+        })
+    }
+
+    /// The running total of samples recorded in the stream, up to and
+    /// including this payload, from `TSMP`.
+    pub fn total_samples(&self) -> Option<u32> {
+        self.children.iter().find_map(|child| match child {
+            Block::TotalSamples(total) => Some(*total),
+            _ => None,
+        })
+    }
+
+    /// The offset in seconds from `TIMO` to apply to the stream's sample times.
+    pub fn time_offset(&self) -> Option<f32> {
+        self.children.iter().find_map(|child| match child {
+            Block::TimeOffset(offset) => Some(*offset),
+            _ => None,
+        })
+    }
+
+    /// The divisors from `SCAL`, one for all components or one per component.
+    pub fn scaling_factors(&self) -> Option<Vec<f64>> {
+        self.children
+            .iter()
+            .any(|child| matches!(child, Block::ScalingFactorS(_) | Block::ScalingFactorL(_)))
+            .then(|| scaling_factors(self.children))
+    }
+
+    /// Like `scaling_factors`, for streams whose samples must be scaled.
+    pub fn require_scaling_factors(&self) -> Result<Vec<f64>, MissingBlock> {
+        self.scaling_factors().ok_or_else(|| self.missing(b"SCAL"))
+    }
+
+    /// Like `start_timestamp`, for streams whose samples must be timed.
+    pub fn require_start_timestamp(&self) -> Result<u64, MissingBlock> {
+        self.start_timestamp().ok_or_else(|| self.missing(b"STMP"))
+    }
+
+    /// Like `name`, for streams that must be identified by name.
+    pub fn require_name(&self) -> Result<&'a str, MissingBlock> {
+        self.name().ok_or_else(|| self.missing(b"STNM"))
+    }
+
+    /// Describes the stream by the FourCC of its samples, or failing that its
+    /// name.
+    fn missing(&self, fourcc: &[u8; 4]) -> MissingBlock {
+        let samples = self
+            .children
+            .iter()
+            .find(|child| child.samples().is_some())
+            .map(|child| child.fourcc().to_string());
+        let container = match samples.or_else(|| self.name().map(str::to_string)) {
+            Some(label) => format!("stream {}", label),
+            None => "stream".to_string(),
+        };
+
+        MissingBlock {
+            container,
+            fourcc: FourCC(*fourcc),
+        }
+    }
+
+    /// The units of the stream's samples from `SIUN`, e.g. `"m/s2"`, or failing
+    /// that from `UNIT`.
+    pub fn units(&self) -> Option<&'a str> {
+        self.si_units().or_else(|| self.display_units())
+    }
+
+    /// The SI units of the stream's samples from `SIUN`.
+    pub fn si_units(&self) -> Option<&'a str> {
+        self.children.iter().find_map(|child| match child {
+            Block::UnitsSI(units) => Some(units.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The display units of the stream's samples from `UNIT`, e.g. `"deg"`.
+    pub fn display_units(&self) -> Option<&'a str> {
+        self.children.iter().find_map(|child| match child {
+            Block::DisplayUnits(units) => Some(units.as_str()),
+            _ => None,
+        })
+    }
+
+    /// The stream's samples as recorded, before `SCAL` is applied, or `None`
+    /// if it has no numeric samples.
+    pub fn raw_samples(&self) -> Option<Samples> {
+        self.children.iter().find_map(Block::samples)
+    }
+
+    /// The stream's scaled samples as one column per component, e.g. x, y and
+    /// z for `ACCL`, or `None` if it has no numeric samples.
+    pub fn as_soa(&self) -> Option<Vec<Vec<f64>>> {
+        let (components, mut values) = self.raw_samples()?.to_f64();
+        apply_scaling(&mut values, components, &scaling_factors(self.children));
+
+        let columns = (0..components)
+            .map(|component| {
+                values
+                    .iter()
+                    .skip(component)
+                    .step_by(components)
+                    .copied()
+                    .collect()
+            })
+            .collect();
+
+        Some(columns)
+    }
+}
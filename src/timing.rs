@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::block::{Block, DeviceId};
+use crate::devc::Devc;
+use crate::fourcc::FourCC;
+use crate::stream::Stream;
+
+/// The start timestamp (`STMP`, in microseconds) and children of every payload
+/// of each stream containing a block matching `predicate`.
+///
+/// Payloads are grouped by device and stream name, as in `stream_sample_times`,
+/// so each group can be timed on its own. Groups and the payloads within them
+/// are in file order.
+pub(crate) fn stream_payloads<F>(blocks: &[Block], predicate: F) -> Vec<Vec<(u64, &[Block])>>
+where
+    F: Fn(&Block) -> bool,
+{
+    type Key<'a> = (Option<DeviceId>, Option<&'a str>);
+    type Payload<'a> = (u64, &'a [Block]);
+    let mut streams: Vec<(Key, Vec<Payload>)> = Vec::new();
+    for block in blocks {
+        // Streams outside a DEVC have no device
+        let (device_id, children) = match block {
+            Block::DeviceSource(children) => (
+                Devc::new(block).and_then(|devc| devc.device_id()),
+                children.as_slice(),
+            ),
+            Block::Stream(_) => (None, std::slice::from_ref(block)),
+            _ => continue,
+        };
+
+        for stream in children.iter().filter_map(Stream::new) {
+            if !stream.children().iter().any(&predicate) {
+                continue;
+            }
+            let start = match stream.start_timestamp() {
+                Some(start) => start,
+                None => continue,
+            };
+
+            let key = (device_id, stream.name());
+            let payload = (start, stream.children());
+            match streams.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, payloads)) => payloads.push(payload),
+                None => streams.push((key, vec![payload])),
+            }
+        }
+    }
+
+    streams.into_iter().map(|(_, payloads)| payloads).collect()
+}
+
+/// Timestamps in seconds for every sample of consecutive payloads of a stream.
+///
+/// Each payload is given as its start timestamp in microseconds and its number
+/// of samples. Samples are spread evenly until the start of the next payload,
+/// and the last payload reuses the sample period of the one before it. A
+/// stream with a single payload has no known period, so all of its samples
+/// are placed at its start.
+pub fn sample_times(payloads: &[(u64, usize)]) -> Vec<f64> {
+    let mut times = Vec::new();
+    let mut period = 0.0;
+    for (index, &(start, count)) in payloads.iter().enumerate() {
+        let start = start as f64 / 1e6;
+        if let Some(&(next_start, _)) = payloads.get(index + 1) {
+            if count > 0 {
+                period = (next_start as f64 / 1e6 - start) / count as f64;
+            }
+        }
+
+        times.extend((0..count).map(|sample| start + sample as f64 * period));
+    }
+
+    times
+}
+
+/// Like `sample_times`, with each payload also given the offset in seconds from
+/// its stream's `TIMO` to add to its samples' times.
+pub(crate) fn offset_sample_times(payloads: &[(u64, usize, f64)]) -> Vec<f64> {
+    let starts: Vec<(u64, usize)> = payloads
+        .iter()
+        .map(|&(start, count, _)| (start, count))
+        .collect();
+    let offsets = payloads
+        .iter()
+        .flat_map(|&(_, count, offset)| std::iter::repeat_n(offset, count));
+
+    sample_times(&starts)
+        .into_iter()
+        .zip(offsets)
+        .map(|(time, offset)| time + offset)
+        .collect()
+}
+
+/// A stream's `TIMO` in seconds, or zero if it has none.
+pub(crate) fn time_offset(children: &[Block]) -> f64 {
+    children
+        .iter()
+        .find_map(|child| match child {
+            Block::TimeOffset(offset) => Some(*offset as f64),
+            _ => None,
+        })
+        .unwrap_or(0.0)
+}
+
+/// The sample rate of each payload of the streams holding `fourcc` blocks, as
+/// pairs of the payload's start time in seconds and its rate in Hz.
+///
+/// The rate of a payload is its number of samples over the time until the
+/// next payload of the same device's stream starts, so drops such as GPS
+/// losing lock show up as dips. The last payload reuses the rate of the one
+/// before it, and a stream with a single payload has a rate of zero. Streams
+/// from different devices are listed one after another.
+pub fn sample_rate_series(blocks: &[Block], fourcc: FourCC) -> Vec<(f64, f64)> {
+    let mut series = Vec::new();
+    for payloads in stream_payloads(blocks, |block| block.fourcc() == fourcc) {
+        let payloads: Vec<(f64, usize)> = payloads
+            .into_iter()
+            .map(|(start, children)| {
+                let count = children
+                    .iter()
+                    .filter(|child| child.fourcc() == fourcc)
+                    .map(Block::sample_count)
+                    .sum();
+                (start as f64 / 1e6 + time_offset(children), count)
+            })
+            .collect();
+
+        let mut rate = 0.0;
+        for (index, &(start, count)) in payloads.iter().enumerate() {
+            if let Some(&(next_start, _)) = payloads.get(index + 1) {
+                if next_start > start {
+                    rate = count as f64 / (next_start - start);
+                }
+            }
+
+            series.push((start, rate));
+        }
+    }
+
+    series
+}
+
+/// Index of the child holding a stream's samples.
+fn samples_index(children: &[Block]) -> Option<usize> {
+    children.iter().position(|child| {
+        child.samples().is_some() || matches!(child, Block::Complex(_, _) | Block::Faces(_))
+    })
+}
+
+/// The time in seconds of every sample of every timed stream, keyed by the
+/// indices of the top-level `DEVC` in `blocks` and of the `STRM` among its
+/// children.
+///
+/// Times come from `sample_times` over each stream's payloads, shifted by any
+/// `TIMO`, matching streams across payloads by device and name.
+pub(crate) fn stream_sample_times(blocks: &[Block]) -> HashMap<(usize, usize), Vec<f64>> {
+    // The payloads of each stream, as block and child indices with their
+    // start timestamp, sample count and time offset
+    type Key<'a> = (Option<DeviceId>, Option<&'a str>);
+    type Payload = ((usize, usize), u64, usize, f64);
+    let mut streams: Vec<(Key, Vec<Payload>)> = Vec::new();
+    for (block_index, devc) in blocks.iter().enumerate() {
+        let devc = match Devc::new(devc) {
+            Some(devc) => devc,
+            None => continue,
+        };
+        for (child_index, child) in devc.children().iter().enumerate() {
+            let stream = match Stream::new(child) {
+                Some(stream) => stream,
+                None => continue,
+            };
+            let (stream_start, samples) =
+                match (stream.start_timestamp(), samples_index(stream.children())) {
+                    (Some(stream_start), Some(samples)) => (stream_start, samples),
+                    _ => continue,
+                };
+
+            let key = (devc.device_id(), stream.name());
+            let count = stream.children()[samples].sample_count();
+            let offset = time_offset(stream.children());
+            let payload = ((block_index, child_index), stream_start, count, offset);
+            match streams.iter_mut().find(|(existing, _)| *existing == key) {
+                Some((_, payloads)) => payloads.push(payload),
+                None => streams.push((key, vec![payload])),
+            }
+        }
+    }
+
+    let mut times = HashMap::new();
+    for (_, payloads) in &streams {
+        let starts: Vec<(u64, usize, f64)> = payloads
+            .iter()
+            .map(|&(_, stream_start, count, offset)| (stream_start, count, offset))
+            .collect();
+        let mut stream_times = offset_sample_times(&starts).into_iter();
+        for &(position, _, count, _) in payloads {
+            times.insert(position, stream_times.by_ref().take(count).collect());
+        }
+    }
+
+    times
+}
+
+/// The top-level `DEVC` payloads overlapping `start..end`, with the samples of
+/// their streams trimmed to the range.
+///
+/// Sample times come from `stream_sample_times`. Trimmed streams have their
+/// `STMP` moved to their first remaining sample. Streams without a `STMP` are
+/// kept whole, but streams with no samples in range are dropped, as are
+/// payloads left without any timed streams.
+pub fn slice_time_range(blocks: &[Block], start: Duration, end: Duration) -> Vec<Block> {
+    let (start, end) = (start.as_secs_f64(), end.as_secs_f64());
+
+    let times = stream_sample_times(blocks);
+
+    let mut sliced = Vec::new();
+    for (block_index, block) in blocks.iter().enumerate() {
+        let children = match block {
+            Block::DeviceSource(children) => children,
+            _ => continue,
+        };
+
+        let mut in_range = false;
+        let mut kept = Vec::new();
+        for (child_index, child) in children.iter().enumerate() {
+            let stream_times = match times.get(&(block_index, child_index)) {
+                Some(stream_times) => stream_times,
+                None => {
+                    kept.push(child.clone());
+                    continue;
+                }
+            };
+
+            let first = stream_times.iter().position(|&time| time >= start);
+            let last = stream_times.iter().rposition(|&time| time < end);
+            let (first, last) = match (first, last) {
+                (Some(first), Some(last)) if first <= last => (first, last),
+                _ => continue,
+            };
+
+            let mut stream = child.clone();
+            if let Block::Stream(stream_children) = &mut stream {
+                if let Some(samples) = samples_index(stream_children) {
+                    stream_children[samples].retain_samples(first..last + 1);
+                }
+                // Sample times include the TIMO, which the STMP doesn't
+                let offset = time_offset(stream_children);
+                for stream_child in stream_children.iter_mut() {
+                    if let Block::StartTimestamp(stream_start) = stream_child {
+                        *stream_start = ((stream_times[first] - offset) * 1e6).round() as u64;
+                    }
+                }
+            }
+            kept.push(stream);
+            in_range = true;
+        }
+
+        if in_range {
+            sliced.push(Block::DeviceSource(kept));
+        }
+    }
+
+    sliced
+}
+
+/// How long the recording lasts, from the start of the first top-level `DEVC`
+/// to the end of the last.
+///
+/// Uses the `TICK` of the first payload and the `TOCK` of the last when both
+/// have them. Otherwise payloads are timed by their `STMP`, either their own or
+/// their first stream's, and the last payload is assumed to last as long as the
+/// one before it. Returns `None` with fewer than two payloads or if the timing
+/// is missing.
+pub fn total_duration(blocks: &[Block]) -> Option<Duration> {
+    let payloads: Vec<&Block> = blocks
+        .iter()
+        .filter(|block| matches!(block, Block::DeviceSource(_)))
+        .collect();
+    let (first, last) = match payloads.as_slice() {
+        [first, .., last] => (*first, *last),
+        _ => return None,
+    };
+
+    if let (Some((tick, _)), Some((_, tock))) = (first.timestamp_range(), last.timestamp_range()) {
+        return tock
+            .checked_sub(tick)
+            .map(|millis| Duration::from_millis(millis as u64));
+    }
+
+    let start_timestamp = |block: &Block| {
+        let devc = Devc::new(block)?;
+        devc.start_timestamp()
+            .or_else(|| devc.streams().find_map(|stream| stream.start_timestamp()))
+    };
+    let start = start_timestamp(first)?;
+    let last_start = start_timestamp(last)?;
+    let previous_start = start_timestamp(payloads[payloads.len() - 2])?;
+    let end = last_start.checked_add(last_start.checked_sub(previous_start)?)?;
+
+    end.checked_sub(start).map(Duration::from_micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::Values;
+    use crate::builder::BlockBuilder;
+
+    #[test]
+    fn rates_each_devices_stream_separately() {
+        let mut builder = BlockBuilder::new();
+        for second in 0..2u64 {
+            builder
+                .device(1)
+                .stream("Gyro")
+                .samples(second * 1_000_000, Block::Gyroscope(vec![[0, 0, 0]; 200]));
+            builder.device(2).stream("Gyro").samples(
+                500_000 + second * 1_000_000,
+                Block::Gyroscope(vec![[0, 0, 0]; 100]),
+            );
+        }
+
+        assert_eq!(
+            sample_rate_series(&builder.build(), FourCC(*b"GYRO")),
+            vec![(0.0, 200.0), (1.0, 200.0), (0.5, 100.0), (1.5, 100.0)]
+        );
+    }
+
+    /// Three one-second payloads of a gyro stream with four samples each,
+    /// numbered in order by their first axis.
+    fn gyro_payloads() -> BlockBuilder {
+        let mut builder = BlockBuilder::new();
+        for payload in 0..3i16 {
+            let samples = (0..4).map(|sample| [payload * 4 + sample, 0, 0]).collect();
+            builder
+                .device(1)
+                .stream("Gyro")
+                .samples(payload as u64 * 1_000_000, Block::Gyroscope(samples));
+        }
+        builder
+    }
+
+    #[test]
+    fn trims_payloads_straddling_the_range() {
+        let blocks = gyro_payloads().build();
+        let sliced = slice_time_range(
+            &blocks,
+            Duration::from_millis(600),
+            Duration::from_millis(1600),
+        );
+
+        let mut expected = BlockBuilder::new();
+        expected
+            .device(1)
+            .stream("Gyro")
+            .samples(750_000, Block::Gyroscope(vec![[3, 0, 0]]));
+        expected.device(1).stream("Gyro").samples(
+            1_000_000,
+            Block::Gyroscope(vec![[4, 0, 0], [5, 0, 0], [6, 0, 0]]),
+        );
+        assert_eq!(sliced, expected.build());
+    }
+
+    #[test]
+    fn keeps_whole_payloads_inside_the_range() {
+        let blocks = gyro_payloads().build();
+        assert_eq!(
+            slice_time_range(&blocks, Duration::ZERO, Duration::from_secs(3)),
+            blocks
+        );
+        assert_eq!(
+            slice_time_range(&blocks, Duration::from_secs(1), Duration::from_secs(2)),
+            blocks[1..2]
+        );
+        assert_eq!(
+            slice_time_range(&blocks, Duration::from_secs(3), Duration::from_secs(4)),
+            vec![]
+        );
+    }
+
+    fn millis(name: &str, millis: u32) -> Block {
+        Block::Generic(name.to_string(), Values::U32(vec![millis]))
+    }
+
+    #[test]
+    fn times_the_recording_by_tick_and_tock() {
+        let mut builder = gyro_payloads();
+        assert_eq!(
+            total_duration(&builder.build()),
+            Some(Duration::from_secs(3))
+        );
+
+        // TICK and TOCK take precedence over the STMPs
+        let mut blocks = builder.build();
+        for (block, (tick, tock)) in
+            blocks
+                .iter_mut()
+                .zip([(100, 1100), (1100, 2100), (2100, 2600)])
+        {
+            if let Block::DeviceSource(children) = block {
+                children.insert(1, millis("TICK", tick));
+                children.insert(2, millis("TOCK", tock));
+            }
+        }
+        assert_eq!(total_duration(&blocks), Some(Duration::from_millis(2500)));
+
+        // Only the first and last payloads need them
+        builder = BlockBuilder::new();
+        builder
+            .device(1)
+            .block(millis("TICK", 100))
+            .block(millis("TOCK", 1100));
+        builder.device(1);
+        builder
+            .device(1)
+            .block(millis("TICK", 2100))
+            .block(millis("TOCK", 2600));
+        assert_eq!(
+            total_duration(&builder.build()),
+            Some(Duration::from_millis(2500))
+        );
+    }
+
+    #[test]
+    fn times_the_recording_by_stream_stmps_without_tick_and_tock() {
+        // The last payload is assumed to last as long as the one before it
+        let mut builder = BlockBuilder::new();
+        for start in [0, 1_000_000, 1_500_000] {
+            builder
+                .device(1)
+                .stream("Gyro")
+                .samples(start, Block::Gyroscope(vec![[0, 0, 0]]));
+        }
+        assert_eq!(
+            total_duration(&builder.build()),
+            Some(Duration::from_secs(2))
+        );
+
+        let blocks = builder.build();
+        assert_eq!(total_duration(&blocks[..1]), None);
+        assert_eq!(total_duration(&[]), None);
+
+        // Untimed payloads have no duration
+        let mut builder = BlockBuilder::new();
+        builder.device(1);
+        builder.device(1);
+        assert_eq!(total_duration(&builder.build()), None);
+    }
+}
@@ -0,0 +1,258 @@
+use std::convert::TryInto;
+
+use crate::fourcc::FourCC;
+use crate::parser::ParseError;
+
+/// The largest payload a block can hold, with a one byte size and two byte
+/// count.
+const MAX_PAYLOAD_SIZE: usize = u8::MAX as usize * u16::MAX as usize;
+
+/// The field layout of a complex (`?`) block, as described by a `TYPE` string
+/// such as `"Lf[6]"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeDescriptor {
+    /// One GPMF data type character per field, with repeats expanded.
+    pub fields: Vec<u8>,
+}
+
+impl TypeDescriptor {
+    /// Parses a `TYPE` string, expanding `[n]` repeats of the preceding field.
+    ///
+    /// Repeats can be chained, so `"f[2][3]"` is six floats. Layouts larger
+    /// than any block could hold are rejected.
+    pub fn parse(descriptor: &str) -> Result<TypeDescriptor, ParseError> {
+        TypeDescriptor::parse_bounded(descriptor, MAX_PAYLOAD_SIZE)
+    }
+
+    /// Like `parse`, but rejects layouts whose records are larger than
+    /// `max_record_size` bytes, as a `TYPE` read from a file can ask for far
+    /// more fields than its stream has data for.
+    pub(crate) fn parse_bounded(
+        descriptor: &str,
+        max_record_size: usize,
+    ) -> Result<TypeDescriptor, ParseError> {
+        // Collect runs of each field first, so nothing is expanded until the
+        // whole layout is known to fit
+        let mut runs: Vec<(u8, usize)> = Vec::new();
+        let mut record_size: usize = 0;
+        let mut bytes = descriptor.bytes();
+        while let Some(byte) = bytes.next() {
+            if byte == b'[' {
+                let mut repeat = String::new();
+                loop {
+                    match bytes.next() {
+                        Some(b']') => break,
+                        Some(digit) if digit.is_ascii_digit() => repeat.push(digit as char),
+                        _ => return Err(ParseError::InvalidTypeDescriptor),
+                    }
+                }
+                let repeat: usize = repeat
+                    .parse()
+                    .map_err(|_| ParseError::InvalidTypeDescriptor)?;
+
+                // Replace the run of the preceding field, so chained repeats multiply
+                let (field, run) = runs.last_mut().ok_or(ParseError::InvalidTypeDescriptor)?;
+                let size = type_size(*field).unwrap_or(0);
+                let expanded = run
+                    .checked_mul(repeat)
+                    .ok_or(ParseError::InvalidTypeDescriptor)?;
+                record_size = expanded
+                    .checked_mul(size)
+                    .and_then(|bytes| (record_size - *run * size).checked_add(bytes))
+                    .ok_or(ParseError::InvalidTypeDescriptor)?;
+                *run = expanded;
+            } else if let Some(size) = type_size(byte) {
+                record_size = record_size
+                    .checked_add(size)
+                    .ok_or(ParseError::InvalidTypeDescriptor)?;
+                runs.push((byte, 1));
+            } else {
+                return Err(ParseError::InvalidTypeDescriptor);
+            }
+
+            if record_size > max_record_size {
+                return Err(ParseError::InvalidTypeDescriptor);
+            }
+        }
+
+        let fields = runs
+            .into_iter()
+            .flat_map(|(field, run)| std::iter::repeat_n(field, run))
+            .collect();
+
+        Ok(TypeDescriptor { fields })
+    }
+
+    /// Size in bytes of one record with this layout.
+    pub fn record_size(&self) -> usize {
+        self.fields
+            .iter()
+            .map(|&field| type_size(field).unwrap_or(0))
+            .sum()
+    }
+
+    /// Splits `data` into records of this layout and decodes each field.
+    ///
+    /// Returns `None` if the layout is empty or `data` isn't a whole number of
+    /// records.
+    pub fn decode(&self, data: &[u8]) -> Option<Vec<Vec<ComplexValue>>> {
+        let record_size = self.record_size();
+        if record_size == 0 || !data.len().is_multiple_of(record_size) {
+            return None;
+        }
+
+        data.chunks(record_size)
+            .map(|record| {
+                let mut offset = 0;
+                self.fields
+                    .iter()
+                    .map(|&field| {
+                        let size = type_size(field)?;
+                        let value = ComplexValue::decode(field, &record[offset..offset + size]);
+                        offset += size;
+                        value
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// A single field of a complex record, decoded according to its data type.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ComplexValue {
+    I8(i8),
+    U8(u8),
+    Char(char),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    F32(f32),
+    FourCC(FourCC),
+    Fixed32(f64), // Q15.16 fixed point
+    I64(i64),
+    U64(u64),
+    F64(f64),
+    Fixed64(f64), // Q31.32 fixed point
+    Guid([u8; 16]),
+    Date(String), // yymmddhhmmss.sss
+}
+
+impl ComplexValue {
+    /// Decodes one value of `data_type` from exactly `type_size(data_type)` bytes.
+    fn decode(data_type: u8, bytes: &[u8]) -> Option<ComplexValue> {
+        let value = match data_type {
+            b'b' => ComplexValue::I8(bytes[0] as i8),
+            b'B' => ComplexValue::U8(bytes[0]),
+            b'c' => ComplexValue::Char(bytes[0] as char),
+            b's' => ComplexValue::I16(i16::from_be_bytes(bytes.try_into().ok()?)),
+            b'S' => ComplexValue::U16(u16::from_be_bytes(bytes.try_into().ok()?)),
+            b'l' => ComplexValue::I32(i32::from_be_bytes(bytes.try_into().ok()?)),
+            b'L' => ComplexValue::U32(u32::from_be_bytes(bytes.try_into().ok()?)),
+            b'f' => ComplexValue::F32(f32::from_be_bytes(bytes.try_into().ok()?)),
+            b'F' => ComplexValue::FourCC(FourCC(bytes.try_into().ok()?)),
+            b'q' => {
+                let raw = i32::from_be_bytes(bytes.try_into().ok()?);
+                ComplexValue::Fixed32(raw as f64 / 65536.0)
+            }
+            b'j' => ComplexValue::I64(i64::from_be_bytes(bytes.try_into().ok()?)),
+            b'J' => ComplexValue::U64(u64::from_be_bytes(bytes.try_into().ok()?)),
+            b'd' => ComplexValue::F64(f64::from_be_bytes(bytes.try_into().ok()?)),
+            b'Q' => {
+                let raw = i64::from_be_bytes(bytes.try_into().ok()?);
+                ComplexValue::Fixed64(raw as f64 / 4_294_967_296.0)
+            }
+            b'G' => ComplexValue::Guid(bytes.try_into().ok()?),
+            b'U' => ComplexValue::Date(String::from_utf8_lossy(bytes).into_owned()),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// The value widened to `f64`, or `None` if it isn't numeric.
+    pub fn to_f64(&self) -> Option<f64> {
+        let value = match self {
+            ComplexValue::I8(value) => *value as f64,
+            ComplexValue::U8(value) => *value as f64,
+            ComplexValue::I16(value) => *value as f64,
+            ComplexValue::U16(value) => *value as f64,
+            ComplexValue::I32(value) => *value as f64,
+            ComplexValue::U32(value) => *value as f64,
+            ComplexValue::F32(value) => *value as f64,
+            ComplexValue::Fixed32(value) | ComplexValue::Fixed64(value) => *value,
+            ComplexValue::I64(value) => *value as f64,
+            ComplexValue::U64(value) => *value as f64,
+            ComplexValue::F64(value) => *value,
+            ComplexValue::Char(_)
+            | ComplexValue::FourCC(_)
+            | ComplexValue::Guid(_)
+            | ComplexValue::Date(_) => return None,
+        };
+
+        Some(value)
+    }
+
+    /// Size in bytes of the value when encoded.
+    pub(crate) fn encoded_size(&self) -> usize {
+        match self {
+            ComplexValue::I8(_) | ComplexValue::U8(_) | ComplexValue::Char(_) => 1,
+            ComplexValue::I16(_) | ComplexValue::U16(_) => 2,
+            ComplexValue::I32(_)
+            | ComplexValue::U32(_)
+            | ComplexValue::F32(_)
+            | ComplexValue::FourCC(_)
+            | ComplexValue::Fixed32(_) => 4,
+            ComplexValue::I64(_)
+            | ComplexValue::U64(_)
+            | ComplexValue::F64(_)
+            | ComplexValue::Fixed64(_) => 8,
+            ComplexValue::Guid(_) => 16,
+            ComplexValue::Date(_) => 16,
+        }
+    }
+}
+
+/// Size in bytes of a single value of a GPMF data type, or `None` for nested,
+/// complex and unknown types.
+pub fn type_size(data_type: u8) -> Option<usize> {
+    match data_type {
+        b'b' | b'B' | b'c' => Some(1),
+        b's' | b'S' => Some(2),
+        b'l' | b'L' | b'f' | b'F' | b'q' => Some(4),
+        b'j' | b'J' | b'd' | b'Q' => Some(8),
+        b'G' | b'U' => Some(16),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_chained_repeats() {
+        let descriptor = TypeDescriptor::parse("Lf[2][3]").unwrap();
+        assert_eq!(descriptor.fields, b"Lffffff");
+        assert_eq!(descriptor.record_size(), 28);
+    }
+
+    #[test]
+    fn rejects_overflowing_repeats() {
+        assert!(matches!(
+            TypeDescriptor::parse("f[4294967296][4294967296]"),
+            Err(ParseError::InvalidTypeDescriptor)
+        ));
+    }
+
+    #[test]
+    fn rejects_records_larger_than_the_data() {
+        assert!(matches!(
+            TypeDescriptor::parse_bounded("f[100000000]", 16),
+            Err(ParseError::InvalidTypeDescriptor)
+        ));
+        assert!(TypeDescriptor::parse_bounded("f[4]", 16).is_ok());
+    }
+}
@@ -0,0 +1,88 @@
+use chrono::{DateTime, Duration, Utc};
+
+use crate::block::Block;
+use crate::devc::Devc;
+use crate::gps::{parse_gpsu, GpsFix};
+use crate::stream::Stream;
+use crate::timing::stream_sample_times;
+
+/// When a sample was recorded.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleTime {
+    Utc(DateTime<Utc>),
+    Relative(f64), // Seconds, as from `sample_times`, for payloads without a GPS fix
+}
+
+/// The sample times of one stream in one top-level `DEVC`.
+#[derive(Debug, Clone)]
+pub struct StreamTimes<'a> {
+    pub devc: usize, // Index of the DEVC in the blocks passed to `wall_clock_times`
+    pub stream: Stream<'a>,
+    pub times: Vec<SampleTime>, // One per sample
+}
+
+/// The time of every sample of every timed stream, in UTC where possible.
+///
+/// Each `DEVC` with a GPS fix is anchored by taking its `GPSU` as the time of
+/// the GPS stream's first sample, and every other sample in the same `DEVC`
+/// is placed relative to that using the `STMP` timebase. Samples in a `DEVC`
+/// without a fix, or without a `GPSU`, keep their relative time.
+pub fn wall_clock_times(blocks: &[Block]) -> Vec<StreamTimes<'_>> {
+    let times = stream_sample_times(blocks);
+
+    let mut stream_times = Vec::new();
+    for (block_index, block) in blocks.iter().enumerate() {
+        let devc = match Devc::new(block) {
+            Some(devc) => devc,
+            None => continue,
+        };
+
+        // The UTC time and relative time in seconds of the same instant
+        let anchor = devc
+            .children()
+            .iter()
+            .enumerate()
+            .find_map(|(child_index, child)| {
+                let children = Stream::new(child)?.children();
+                let fix = children.iter().find_map(|block| match block {
+                    Block::GPSF(fix) => Some(GpsFix::from(*fix)),
+                    _ => None,
+                });
+                if fix == Some(GpsFix::NoLock) {
+                    return None;
+                }
+                let utc = children.iter().find_map(|block| match block {
+                    Block::GPSTimestamp(gpsu) => parse_gpsu(gpsu),
+                    _ => None,
+                })?;
+                let relative = *times.get(&(block_index, child_index))?.first()?;
+                Some((utc, relative))
+            });
+
+        for (child_index, child) in devc.children().iter().enumerate() {
+            let (stream, relative_times) =
+                match (Stream::new(child), times.get(&(block_index, child_index))) {
+                    (Some(stream), Some(relative_times)) => (stream, relative_times),
+                    _ => continue,
+                };
+
+            let times = relative_times
+                .iter()
+                .map(|&time| match anchor {
+                    Some((utc, relative)) => {
+                        let micros = ((time - relative) * 1e6).round() as i64;
+                        SampleTime::Utc(utc + Duration::microseconds(micros))
+                    }
+                    None => SampleTime::Relative(time),
+                })
+                .collect();
+            stream_times.push(StreamTimes {
+                devc: block_index,
+                stream,
+                times,
+            });
+        }
+    }
+
+    stream_times
+}
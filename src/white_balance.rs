@@ -0,0 +1,43 @@
+use std::convert::TryFrom;
+
+use crate::block::Block;
+use crate::stream::Stream;
+
+/// The white balance a stream records, from whichever of `WBAL` and `WRGB` it
+/// carries.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WhiteBalance {
+    pub kelvin: Option<u16>,         // WBAL
+    pub rgb_gains: Option<[f32; 3]>, // WRGB
+}
+
+/// The first `WBAL` colour temperature and `WRGB` gains in the stream, or
+/// `None` if it has neither. Signed and floating point temperatures are
+/// rounded to whole kelvin, and negative ones ignored.
+pub fn white_balance(stream: &Stream) -> Option<WhiteBalance> {
+    let children = stream.children();
+
+    let balance = WhiteBalance {
+        kelvin: children.iter().find_map(|child| match child {
+            Block::WhiteBalance(measurements) => measurements.first().copied(),
+            Block::WhiteBalanceSigned(measurements) => measurements
+                .first()
+                .and_then(|&kelvin| u16::try_from(kelvin).ok()),
+            Block::WhiteBalanceFloat(measurements) => measurements
+                .first()
+                .filter(|kelvin| (0.0..=f32::from(u16::MAX)).contains(*kelvin))
+                .map(|kelvin| kelvin.round() as u16),
+            _ => None,
+        }),
+        rgb_gains: children.iter().find_map(|child| match child {
+            Block::WhiteBalanceRGBGains(measurements) => measurements.first().copied(),
+            _ => None,
+        }),
+    };
+
+    if balance.kelvin.is_none() && balance.rgb_gains.is_none() {
+        return None;
+    }
+
+    Some(balance)
+}